@@ -0,0 +1,20 @@
+//! Exposes the wgpu rev (or version) `Cargo.toml` currently selects as
+//! `WGPU_REVISION`, so results say which side of the regression they ran on.
+
+use std::fs;
+
+fn main() {
+    println!("cargo:rerun-if-changed=Cargo.toml");
+    let manifest = fs::read_to_string("Cargo.toml").expect("read Cargo.toml");
+    let revision = manifest
+        .lines()
+        .find(|line| line.starts_with("wgpu ="))
+        .and_then(|line| {
+            ["rev = \"", "version = \""].iter().find_map(|key| {
+                let rest = &line[line.find(key)? + key.len()..];
+                Some(&rest[..rest.find('"')?])
+            })
+        })
+        .unwrap_or("unknown");
+    println!("cargo:rustc-env=WGPU_REVISION={revision}");
+}
@@ -0,0 +1,153 @@
+//! `async [--executor=local|pollster|tokio] [--runs=N] [--poll-interval-ms=N]`:
+//! run the scenario with every GPU wait done through callbacks, fired by a
+//! background [`Poller`] rather than by blocking `poll` calls.
+//!
+//! `local` interleaves all runs on one thread and `pollster` runs them one
+//! after another, both on one device. `tokio` (with the `tokio` feature)
+//! spawns each as a task of a multi-threaded runtime, on a device and
+//! poller of its own: error scopes belong to the device, so runs sharing
+//! one across threads couldn't tell their validation errors apart.
+
+use std::future::Future;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use std::time::Duration;
+
+use crate::cli::Args;
+use crate::harness::{self, Extras, Gpu};
+use crate::nonblocking::Poller;
+use crate::reference;
+use crate::results::{self, Record};
+use crate::scenario::Scenario;
+
+const RUNS: usize = 4;
+const POLL_INTERVAL_MS: u64 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Executor {
+    Local,
+    Pollster,
+    #[cfg(feature = "tokio")]
+    Tokio,
+}
+
+fn parse_executor(s: &str) -> Option<Executor> {
+    Some(match s {
+        "local" => Executor::Local,
+        "pollster" => Executor::Pollster,
+        #[cfg(feature = "tokio")]
+        "tokio" => Executor::Tokio,
+        _ => return None,
+    })
+}
+
+/// Returns `false` if any run raised errors or disagreed with the reference.
+pub fn run(args: &Args, scenario: &Scenario) -> bool {
+    let executor = args
+        .parse("executor", parse_executor)
+        .unwrap_or(Executor::Local);
+    let runs = args
+        .parse("runs", |s| s.parse().ok().filter(|&n| n > 0))
+        .unwrap_or(RUNS);
+    let interval = Duration::from_millis(
+        args.parse("poll-interval-ms", |s| s.parse().ok())
+            .unwrap_or(POLL_INTERVAL_MS),
+    );
+
+    // A device with its poller, dropped once the runs on it are done.
+    let open = || {
+        let gpu = Arc::new(pollster::block_on(harness::init(scenario)));
+        let poller = Poller::start(gpu.clone(), interval);
+        (gpu, poller)
+    };
+    println!("{runs} runs on {executor:?}, polling every {interval:?}");
+    let results: Vec<bool> = match executor {
+        Executor::Local => {
+            let (gpu, _poller) = open();
+            run_local(
+                (0..runs)
+                    .map(|index| run_one(&gpu, scenario, index))
+                    .collect(),
+            )
+        }
+        Executor::Pollster => {
+            let (gpu, _poller) = open();
+            (0..runs)
+                .map(|index| pollster::block_on(run_one(&gpu, scenario, index)))
+                .collect()
+        }
+        #[cfg(feature = "tokio")]
+        Executor::Tokio => {
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            let handles: Vec<_> = (0..runs)
+                .map(|index| {
+                    let scenario = scenario.clone();
+                    runtime.spawn(async move {
+                        // Concurrent runs on one device would pop each
+                        // other's error scopes, so each task gets its own.
+                        let gpu = Arc::new(harness::init(&scenario).await);
+                        let _poller = Poller::start(gpu.clone(), interval);
+                        run_one(&gpu, &scenario, index).await
+                    })
+                })
+                .collect();
+            runtime.block_on(async {
+                let mut results = Vec::new();
+                for handle in handles {
+                    // A panicked run fails like one that disagreed.
+                    results.push(handle.await.unwrap_or(false));
+                }
+                results
+            })
+        }
+    };
+    results.into_iter().all(|ok| ok)
+}
+
+async fn run_one(gpu: &Gpu, scenario: &Scenario, index: usize) -> bool {
+    let extras = Extras {
+        nonblocking: true,
+        ..Default::default()
+    };
+    let outcome = harness::run_with(gpu, scenario, extras).await;
+    println!("== run {index}");
+    outcome.print();
+    let mut record = Record::new(gpu, format!("run {index}"), scenario);
+    record.add_outcome(&outcome);
+    record.compare(&outcome, &reference::render(scenario), reference::TOLERANCE);
+    results::push(record)
+}
+
+struct Unpark(Thread);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// A single-threaded executor: polls every future on the calling thread,
+/// parking whenever none of them can make progress.
+fn run_local<F: Future>(futures: Vec<F>) -> Vec<F::Output> {
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut futures: Vec<_> = futures.into_iter().map(Box::pin).collect();
+    let mut outputs: Vec<Option<F::Output>> = futures.iter().map(|_| None).collect();
+    loop {
+        for (future, output) in futures.iter_mut().zip(&mut outputs) {
+            if output.is_none() {
+                if let Poll::Ready(value) = future.as_mut().poll(&mut cx) {
+                    *output = Some(value);
+                }
+            }
+        }
+        if outputs.iter().all(Option::is_some) {
+            return outputs.into_iter().map(Option::unwrap).collect();
+        }
+        thread::park();
+    }
+}
@@ -0,0 +1,109 @@
+//! `bench [--iterations=N]`: run the scenario repeatedly and print median and
+//! p95 timings, to compare the wgpu revs in `Cargo.toml` for speed as well
+//! as for correctness.
+//!
+//! CPU times come from [`Outcome::timings`] plus `request_device`; GPU time
+//! of the render pass comes from timestamp queries when the adapter has
+//! `TIMESTAMP_QUERY`.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use wgpu::{Features, QuerySet, QuerySetDescriptor, QueryType};
+
+use crate::cli::Args;
+use crate::harness::{self, Extras, Gpu, Outcome};
+use crate::instance;
+use crate::scenario::Scenario;
+
+const ITERATIONS: usize = 100;
+
+/// Returns `false` if any iteration raised errors.
+pub async fn run(args: &Args, scenario: &Scenario) -> bool {
+    let iterations = args
+        .parse("iterations", |s| s.parse().ok().filter(|&n| n > 0))
+        .unwrap_or(ITERATIONS);
+    let mut samples: Vec<(&'static str, Vec<Duration>)> = Vec::new();
+
+    // Only the device request is timed, with the adapter already in hand.
+    let instance = Arc::new(instance::new(&scenario.instance));
+    let mut device_creation = Vec::with_capacity(iterations);
+    for _ in 0..iterations {
+        let adapter = harness::request_adapter(&instance).await;
+        let start = Instant::now();
+        let gpu = harness::open(instance.clone(), &scenario.instance, adapter).await;
+        device_creation.push(start.elapsed());
+        drop(gpu);
+    }
+    samples.push(("request_device", device_creation));
+
+    let gpu = harness::init(scenario).await;
+    println!("{:?}", gpu.adapter.get_info());
+    let timestamps = gpu
+        .device
+        .features()
+        .contains(Features::TIMESTAMP_QUERY)
+        .then(|| {
+            gpu.device.create_query_set(&QuerySetDescriptor {
+                label: None,
+                ty: QueryType::Timestamp,
+                count: 2,
+            })
+        });
+    if timestamps.is_none() {
+        println!("no TIMESTAMP_QUERY, GPU time not measured");
+    }
+
+    let mut ok = true;
+    let mut pass_times = Vec::new();
+    for _ in 0..iterations {
+        let extras = Extras {
+            timestamps: timestamps.as_ref(),
+            ..Default::default()
+        };
+        let outcome = harness::run_with(&gpu, scenario, extras).await;
+        if !outcome.errors.is_empty() {
+            outcome.print();
+            ok = false;
+        }
+        record(&mut samples, &outcome);
+        // A failed render never wrote the timestamps.
+        if let (Some(query_set), Some(_)) = (&timestamps, &outcome.pixels) {
+            pass_times.extend(pass_time(&gpu, query_set));
+        }
+    }
+    if !pass_times.is_empty() {
+        samples.push(("gpu render pass", pass_times));
+    }
+
+    println!("{iterations} iterations");
+    for (name, durations) in &mut samples {
+        durations.sort();
+        println!(
+            "{name:<24} median {:>12?}  p95 {:>12?}",
+            durations[durations.len() / 2],
+            durations[(durations.len() * 95).div_ceil(100) - 1],
+        );
+    }
+    ok
+}
+
+fn record(samples: &mut Vec<(&'static str, Vec<Duration>)>, outcome: &Outcome) {
+    for &(name, duration) in &outcome.timings {
+        match samples.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, durations)) => durations.push(duration),
+            None => samples.push((name, vec![duration])),
+        }
+    }
+}
+
+/// The time between the two timestamps the render pass wrote into `query_set`.
+fn pass_time(gpu: &Gpu, query_set: &QuerySet) -> Option<Duration> {
+    let [start, end] = harness::resolve_queries(gpu, query_set, 2)?[..] else {
+        return None;
+    };
+    let ticks = end.checked_sub(start)?;
+    Some(Duration::from_nanos(
+        (ticks as f64 * gpu.queue.get_timestamp_period() as f64) as u64,
+    ))
+}
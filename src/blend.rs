@@ -0,0 +1,89 @@
+//! `blend`: sweep blend states rendering into `view01` over existing
+//! contents, against a reference that blends in linear space and encodes
+//! to sRGB afterwards.
+
+use wgpu::{BlendComponent, BlendFactor, BlendOperation, BlendState, Features, LoadOp};
+
+use crate::harness::{self, Gpu};
+use crate::reference;
+use crate::results::{self, Record};
+use crate::scenario::Scenario;
+
+/// sRGB attachments may blend at reduced precision before re-encoding.
+pub const TOLERANCE: u8 = 2;
+
+fn both(component: BlendComponent) -> BlendState {
+    BlendState {
+        color: component,
+        alpha: component,
+    }
+}
+
+fn component(src: BlendFactor, dst: BlendFactor, operation: BlendOperation) -> BlendComponent {
+    BlendComponent {
+        src_factor: src,
+        dst_factor: dst,
+        operation,
+    }
+}
+
+pub fn presets() -> Vec<(&'static str, BlendState)> {
+    use BlendFactor::*;
+    use BlendOperation::*;
+    vec![
+        ("alpha", BlendState::ALPHA_BLENDING),
+        ("premultiplied", BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+        ("additive", both(component(One, One, Add))),
+        ("subtract", both(component(One, One, Subtract))),
+        (
+            "reverse-subtract",
+            both(component(One, One, ReverseSubtract)),
+        ),
+        ("multiply", both(component(Dst, Zero, Add))),
+        ("min", both(component(One, One, Min))),
+        ("max", both(component(One, One, Max))),
+        (
+            "saturate",
+            BlendState {
+                color: component(SrcAlphaSaturated, One, Add),
+                alpha: component(One, Zero, Add),
+            },
+        ),
+        (
+            "dual-source",
+            BlendState {
+                color: component(Src1, OneMinusSrc1, Add),
+                alpha: component(Src1Alpha, OneMinusSrc1Alpha, Add),
+            },
+        ),
+    ]
+}
+
+/// Returns `false` if any blend state raised errors or disagreed with the reference.
+pub async fn run(gpu: &Gpu, scenario: &Scenario) -> bool {
+    let dual_source = gpu
+        .device
+        .features()
+        .contains(Features::DUAL_SOURCE_BLENDING);
+    let mut ok = true;
+    for (name, blend) in presets() {
+        let scenario = Scenario {
+            blend: Some(blend),
+            load: LoadOp::Load,
+            ..scenario.clone()
+        };
+        let mut record = Record::new(gpu, name, &scenario);
+        if scenario.dual_source() && !dual_source {
+            println!("== {name}: skipped, no DUAL_SOURCE_BLENDING");
+            results::skip(record, "no DUAL_SOURCE_BLENDING");
+            continue;
+        }
+        println!("== {name}");
+        let outcome = harness::run(gpu, &scenario).await;
+        outcome.print();
+        record.add_outcome(&outcome);
+        record.compare(&outcome, &reference::render(&scenario), TOLERANCE);
+        ok &= results::push(record);
+    }
+    ok
+}
@@ -0,0 +1,62 @@
+//! Minimal argument handling: `<command> [positional..] [--flag] [--key=value]`.
+
+pub struct Args {
+    pub command: Option<String>,
+    positional: Vec<String>,
+    options: Vec<(String, Option<String>)>,
+}
+
+impl Args {
+    pub fn from_env() -> Self {
+        let mut args = std::env::args().skip(1).peekable();
+        // Options alone, e.g. `--json=PATH`, run the default command.
+        let command = args.next_if(|arg| !arg.starts_with("--"));
+        let mut positional = Vec::new();
+        let mut options = Vec::new();
+        for arg in args {
+            match arg.strip_prefix("--") {
+                Some(opt) => match opt.split_once('=') {
+                    Some((key, value)) => options.push((key.to_string(), Some(value.to_string()))),
+                    None => options.push((opt.to_string(), None)),
+                },
+                None => positional.push(arg),
+            }
+        }
+        Args {
+            command,
+            positional,
+            options,
+        }
+    }
+
+    pub fn positional(&self, index: usize) -> Option<&str> {
+        self.positional.get(index).map(String::as_str)
+    }
+
+    /// `--key` given without a value.
+    pub fn flag(&self, key: &str) -> bool {
+        self.options.iter().any(|(k, v)| k == key && v.is_none())
+    }
+
+    /// Value of the last `--key=value`.
+    pub fn value(&self, key: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .and_then(|(_, v)| v.as_deref())
+    }
+
+    /// Parses `--key=value` with `parse`, exiting with a usage error if it
+    /// doesn't recognize the value.
+    pub fn parse<T>(&self, key: &str, parse: impl Fn(&str) -> Option<T>) -> Option<T> {
+        let value = self.value(key)?;
+        match parse(value) {
+            Some(parsed) => Some(parsed),
+            None => {
+                eprintln!("unrecognized value for --{key}: {value}");
+                std::process::exit(2);
+            }
+        }
+    }
+}
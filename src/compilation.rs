@@ -0,0 +1,70 @@
+//! `compilation`: sweep `PipelineCompilationOptions` over sets of
+//! pipeline-overridable constants, checking every combination against the
+//! reference and against the default options for the same constants.
+
+use crate::harness::{self, Gpu};
+use crate::reference;
+use crate::results::{self, Record};
+use crate::scenario::{Compilation, Scenario};
+
+/// Constant sets for `SHADER_OVERRIDE`; the empty set keeps `SHADER11`.
+pub const CONSTANTS: &[&[(&str, f64)]] = &[
+    &[],
+    &[("gain", 1.0)],
+    &[("gain", 0.5), ("bias", 0.25)],
+    &[("gain", 0.5), ("0", 0.5)],
+    &[("bias", -0.125), ("0", 0.0)],
+];
+
+/// Returns `false` if any combination raised errors, disagreed with the
+/// reference, or differed from the default options.
+pub async fn run(gpu: &Gpu, scenario: &Scenario) -> bool {
+    let mut ok = true;
+    for constants in CONSTANTS {
+        let mut baseline = None;
+        for (zero_initialize_workgroup_memory, vertex_pulling_transform) in
+            [(true, false), (false, false), (true, true), (false, true)]
+        {
+            let compilation = Compilation {
+                constants: constants.iter().map(|&(k, v)| (k.to_string(), v)).collect(),
+                zero_initialize_workgroup_memory,
+                vertex_pulling_transform,
+            };
+            let name = format!("{compilation:?}");
+            println!("== {name}");
+            let scenario = Scenario {
+                compilation,
+                ..scenario.clone()
+            };
+            let outcome = harness::run(gpu, &scenario).await;
+            outcome.print();
+            let mut record = Record::new(gpu, name, &scenario);
+            record.add_outcome(&outcome);
+            record.compare(
+                &outcome,
+                &reference::render(&scenario),
+                reference::TOLERANCE,
+            );
+
+            let steps: Vec<_> = outcome.errors.iter().map(|err| err.step).collect();
+            match &baseline {
+                None => baseline = Some((steps, outcome.pixels)),
+                Some((base_steps, base_pixels)) => {
+                    if *base_steps != steps {
+                        let reason = format!(
+                            "failing steps differ from defaults: {base_steps:?} vs {steps:?}"
+                        );
+                        println!("{reason}");
+                        record.fail(reason);
+                    }
+                    if *base_pixels != outcome.pixels {
+                        println!("pixels differ from defaults");
+                        record.fail("pixels differ from defaults");
+                    }
+                }
+            }
+            ok &= results::push(record);
+        }
+    }
+    ok
+}
@@ -0,0 +1,260 @@
+//! `compute`: the same `textureLoad` of `tex11`, through the scenario's
+//! `source_view_format` view, done from a compute shader, written through a
+//! storage texture and a storage buffer, checked against the CPU reference
+//! next to the render path.
+//!
+//! The storage side always uses `rgba8unorm`, regardless of the scenario's
+//! view format: it is the unfiltered, non-sRGB form of `DATA`'s texels, so
+//! what the shader stores reads back byte for byte as what it loaded, and
+//! every adapter supports it as a write-only storage texture.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BufferDescriptor, BufferUsages, CommandEncoderDescriptor,
+    ComputePassDescriptor, ComputePipelineDescriptor, Extent3d, ImageCopyBuffer,
+    PipelineCompilationOptions, ShaderModuleDescriptor, ShaderSource, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureUsages, TextureViewDescriptor,
+};
+
+use crate::harness::{self, step, Gpu, StepError, ROW_PITCH};
+use crate::reference;
+use crate::results::{self, Record};
+use crate::scenario::{Scenario, SIZE};
+use crate::shaders;
+use crate::DATA;
+
+/// Storage texture format `SHADER_COMPUTE` declares.
+const STORAGE_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+
+#[derive(Debug, Default)]
+pub struct ComputeOutcome {
+    pub errors: Vec<StepError>,
+    /// Tightly packed texels of the storage texture.
+    pub texture: Option<Vec<u8>>,
+    /// One `vec4<f32>` per texel, as written to the storage buffer.
+    pub buffer: Option<Vec<[f32; 4]>>,
+}
+
+pub async fn run_compute(gpu: &Gpu, scenario: &Scenario) -> ComputeOutcome {
+    let Gpu { device, queue, .. } = gpu;
+    let mut errors = Vec::new();
+    let size = Extent3d {
+        width: SIZE,
+        height: SIZE,
+        depth_or_array_layers: 1,
+    };
+    let buffer_size = (SIZE * SIZE * 16) as u64;
+
+    let (tex11, storage_texture, storage_buffer) =
+        step(device, &mut errors, "create_resources", || {
+            let tex11 = device.create_texture(&TextureDescriptor {
+                label: None,
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: scenario.format,
+                usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
+                view_formats: harness::view_formats(
+                    scenario.format,
+                    scenario.source_view_format.as_ref(),
+                ),
+            });
+            let storage_texture = device.create_texture(&TextureDescriptor {
+                label: None,
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: STORAGE_FORMAT,
+                usage: TextureUsages::COPY_SRC | TextureUsages::STORAGE_BINDING,
+                view_formats: &[],
+            });
+            let storage_buffer = device.create_buffer(&BufferDescriptor {
+                label: None,
+                size: buffer_size,
+                usage: BufferUsages::COPY_SRC | BufferUsages::STORAGE,
+                mapped_at_creation: false,
+            });
+            (tex11, storage_texture, storage_buffer)
+        })
+        .await;
+
+    step(device, &mut errors, "upload", || {
+        harness::upload(gpu, &tex11, &DATA)
+    })
+    .await;
+
+    let pipeline = step(device, &mut errors, "create_compute_pipeline", || {
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: None,
+            source: ShaderSource::Wgsl(Cow::Borrowed(shaders::SHADER_COMPUTE)),
+        });
+        device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: None,
+            layout: None,
+            module: &module,
+            entry_point: "main",
+            compilation_options: PipelineCompilationOptions {
+                constants: &HashMap::new(),
+                zero_initialize_workgroup_memory: true,
+                vertex_pulling_transform: false,
+            },
+            cache: None,
+        })
+    })
+    .await;
+
+    let bind_group = step(device, &mut errors, "create_bind_group", || {
+        let view11 = tex11.create_view(&TextureViewDescriptor {
+            format: scenario.source_view_format,
+            ..Default::default()
+        });
+        let storage_view = storage_texture.create_view(&TextureViewDescriptor::default());
+        device.create_bind_group(&BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view11),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&storage_view),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: storage_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    })
+    .await;
+
+    let texture_readback = device.create_buffer(&BufferDescriptor {
+        label: None,
+        size: (ROW_PITCH * SIZE) as u64,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let buffer_readback = device.create_buffer(&BufferDescriptor {
+        label: None,
+        size: buffer_size,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    step(device, &mut errors, "dispatch", || {
+        let mut cmd_enc = device.create_command_encoder(&CommandEncoderDescriptor { label: None });
+        {
+            let mut pass = cmd_enc.begin_compute_pass(&ComputePassDescriptor {
+                label: None,
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(SIZE / 8, SIZE / 8, 1);
+        }
+        cmd_enc.copy_texture_to_buffer(
+            storage_texture.as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &texture_readback,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(ROW_PITCH),
+                    rows_per_image: Some(SIZE),
+                },
+            },
+            size,
+        );
+        cmd_enc.copy_buffer_to_buffer(&storage_buffer, 0, &buffer_readback, 0, buffer_size);
+        queue.submit([cmd_enc.finish()]);
+    })
+    .await;
+
+    if errors.iter().any(|err| err.step == "dispatch") {
+        return ComputeOutcome {
+            errors,
+            ..Default::default()
+        };
+    }
+    let texture = harness::read_texels(device, &texture_readback);
+    let buffer = harness::read_buffer(device, &buffer_readback).map(|bytes| {
+        bytes
+            .chunks(16)
+            .map(|v| {
+                std::array::from_fn(|i| f32::from_le_bytes(v[i * 4..][..4].try_into().unwrap()))
+            })
+            .collect()
+    });
+    ComputeOutcome {
+        errors,
+        texture,
+        buffer,
+    }
+}
+
+/// Compares the storage buffer with [`reference::loaded`], counting texels
+/// with a channel more than half a unorm step away.
+fn compare_loaded(expected: &[[f32; 4]], actual: &[[f32; 4]]) -> usize {
+    expected
+        .iter()
+        .zip(actual)
+        .filter(|(e, a)| {
+            e.iter()
+                .zip(a.iter())
+                .any(|(e, a)| (e - a).abs() > 0.5 / 255.0)
+        })
+        .count()
+}
+
+/// Returns `false` if either path raised errors or disagreed with the reference.
+pub async fn run(gpu: &Gpu, scenario: &Scenario) -> bool {
+    println!("== compute");
+    let compute = run_compute(gpu, scenario).await;
+    for err in &compute.errors {
+        println!("ERROR in {}: {}", err.step, err.message);
+    }
+    let mut record = Record::new(gpu, "compute", scenario);
+    record.add_errors(&compute.errors);
+    let expected_texture: Vec<u8> = reference::loaded(scenario)
+        .into_iter()
+        .flat_map(|v| reference::encode(STORAGE_FORMAT, v))
+        .collect();
+    match &compute.texture {
+        Some(texture) => {
+            let comparison = reference::compare(&expected_texture, texture);
+            println!("storage texture vs reference: {comparison:?}");
+            record.comparison = Some(comparison);
+            record.tolerance = Some(reference::TOLERANCE);
+        }
+        None => {
+            println!("storage texture: no readback");
+            record.fail("storage texture: no readback");
+        }
+    }
+    match &compute.buffer {
+        Some(buffer) => {
+            let mismatched = compare_loaded(&reference::loaded(scenario), buffer);
+            println!("storage buffer vs reference: {mismatched} mismatched texels");
+            if mismatched != 0 {
+                record.fail(format!("storage buffer: {mismatched} mismatched texels"));
+            }
+        }
+        None => {
+            println!("storage buffer: no readback");
+            record.fail("storage buffer: no readback");
+        }
+    }
+    let ok = results::push(record);
+
+    println!("== render");
+    let render = harness::run(gpu, scenario).await;
+    render.print();
+    let mut record = Record::new(gpu, "render", scenario);
+    record.add_outcome(&render);
+    record.compare(&render, &reference::render(scenario), reference::TOLERANCE);
+    results::push(record) && ok
+}
@@ -0,0 +1,134 @@
+//! `copy`: move `DATA` through `copy_texture_to_texture` between the linear
+//! and sRGB variants of the scenario's format, and through
+//! `copy_texture_to_buffer`, without any shader involved.
+//!
+//! Copies never convert, so every case must return `DATA` bit-for-bit.
+
+use wgpu::{
+    BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Extent3d, ImageCopyBuffer,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+};
+
+use crate::harness::{self, step, Gpu, Outcome, ROW_PITCH};
+use crate::reference;
+use crate::results::{self, Record};
+use crate::scenario::{Scenario, SIZE};
+use crate::DATA;
+
+/// Uploads into a `src` texture, optionally copies that into a `dst`
+/// texture, and reads back whichever was written last.
+pub async fn run_copy(gpu: &Gpu, src: TextureFormat, dst: Option<TextureFormat>) -> Outcome {
+    let Gpu { device, queue, .. } = gpu;
+    let mut errors = Vec::new();
+    let size = Extent3d {
+        width: SIZE,
+        height: SIZE,
+        depth_or_array_layers: 1,
+    };
+    let texture = |format| {
+        device.create_texture(&TextureDescriptor {
+            label: None,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::COPY_SRC | TextureUsages::COPY_DST,
+            view_formats: &[],
+        })
+    };
+
+    let (src_texture, dst_texture) = step(device, &mut errors, "create_textures", || {
+        (texture(src), dst.map(texture))
+    })
+    .await;
+
+    step(device, &mut errors, "upload", || {
+        harness::upload(gpu, &src_texture, &DATA)
+    })
+    .await;
+
+    let readback = device.create_buffer(&BufferDescriptor {
+        label: None,
+        size: (ROW_PITCH * SIZE) as u64,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    step(device, &mut errors, "copy", || {
+        let mut cmd_enc = device.create_command_encoder(&CommandEncoderDescriptor { label: None });
+        if let Some(dst_texture) = &dst_texture {
+            cmd_enc.copy_texture_to_texture(
+                src_texture.as_image_copy(),
+                dst_texture.as_image_copy(),
+                size,
+            );
+        }
+        cmd_enc.copy_texture_to_buffer(
+            dst_texture.as_ref().unwrap_or(&src_texture).as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &readback,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(ROW_PITCH),
+                    rows_per_image: Some(SIZE),
+                },
+            },
+            size,
+        );
+        queue.submit([cmd_enc.finish()]);
+    })
+    .await;
+
+    let copy_ok = !errors.iter().any(|err| err.step == "copy");
+    let pixels = copy_ok
+        .then(|| harness::read_texels(device, &readback))
+        .flatten();
+    Outcome {
+        errors,
+        pixels,
+        ..Default::default()
+    }
+}
+
+/// Returns `false` if any copy raised an error or altered a byte.
+pub async fn run(gpu: &Gpu, scenario: &Scenario) -> bool {
+    let linear = scenario.format.remove_srgb_suffix();
+    let srgb = scenario.format.add_srgb_suffix();
+    let cases = [
+        (linear, None),
+        (srgb, None),
+        (linear, Some(linear)),
+        (linear, Some(srgb)),
+        (srgb, Some(linear)),
+        (srgb, Some(srgb)),
+    ];
+    let expected = reference::texels();
+    let mut ok = true;
+    for (src, dst) in cases {
+        let name = match dst {
+            Some(dst) => format!("{src:?} -> {dst:?} -> buffer"),
+            None => format!("{src:?} -> buffer"),
+        };
+        println!("== {name}");
+        let outcome = run_copy(gpu, src, dst).await;
+        outcome.print();
+        let mut record = Record::new(gpu, name, scenario);
+        // The copy's formats stand in for the scenario's view formats: the
+        // bytes end up read as `dst`, or as `src` without a second texture.
+        record
+            .parameters
+            .insert("format".to_string(), format!("{src:?}"));
+        record.parameters.insert(
+            "view_format".to_string(),
+            format!("{:?}", dst.unwrap_or(src)),
+        );
+        record.parameters.insert(
+            "copy_dst".to_string(),
+            dst.map_or_else(|| "none".to_string(), |dst| format!("{dst:?}")),
+        );
+        record.add_outcome(&outcome);
+        record.compare(&outcome, &expected, 0);
+        ok &= results::push(record);
+    }
+    ok
+}
@@ -0,0 +1,104 @@
+//! `device-loss`: destroy the device at each [`Destroy`] point of a run and
+//! check that wgpu reports the loss instead of panicking, that the pending
+//! readback fails, and that a fresh device runs the scenario correctly.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+
+use wgpu::{DeviceLostReason, Maintain};
+
+use crate::harness::{self, Outcome};
+use crate::reference;
+use crate::results::{self, Record};
+use crate::scenario::{Destroy, Scenario};
+
+/// Returns `false` if any loss panicked, went unreported or still produced
+/// pixels, or if a fresh device failed the scenario afterwards.
+pub fn run(scenario: &Scenario) -> bool {
+    let mut ok = true;
+    for destroy in [
+        Destroy::AfterUpload,
+        Destroy::BeforeSubmit,
+        Destroy::DuringMap,
+    ] {
+        println!("== destroy {destroy:?}");
+        let lost = Arc::new(Mutex::new(None));
+        let lossy = Scenario {
+            destroy: Some(destroy),
+            ..scenario.clone()
+        };
+        let gpu = pollster::block_on(harness::init(scenario));
+        let mut record = Record::new(&gpu, format!("destroy {destroy:?}"), &lossy);
+        let sink = lost.clone();
+        // Moved in, so a device wgpu panicked over is dropped while unwinding.
+        let result = panic::catch_unwind(AssertUnwindSafe(move || {
+            gpu.device.set_device_lost_callback(move |reason, message| {
+                *sink.lock().unwrap() = Some((reason, message));
+            });
+            let outcome = pollster::block_on(harness::run(&gpu, &lossy));
+            // Delivers the callback, and reads it before dropping the device
+            // would report `Dropped` instead.
+            gpu.device.poll(Maintain::Wait);
+            let lost = lost.lock().unwrap().take();
+            (outcome, lost)
+        }));
+        match result {
+            Ok((outcome, lost)) => check(destroy, &outcome, lost, &mut record),
+            Err(payload) => {
+                let message = harness::panic_message(&*payload);
+                println!("panicked after the device was destroyed: {message}");
+                record.fail(format!(
+                    "panicked after the device was destroyed: {message}"
+                ));
+            }
+        }
+        ok &= results::push(record);
+
+        println!("== fresh device after {destroy:?}");
+        let gpu = pollster::block_on(harness::init(scenario));
+        let outcome = pollster::block_on(harness::run(&gpu, scenario));
+        outcome.print();
+        let mut record = Record::new(&gpu, format!("fresh device after {destroy:?}"), scenario);
+        record.add_outcome(&outcome);
+        record.compare(&outcome, &reference::render(scenario), reference::TOLERANCE);
+        ok &= results::push(record);
+    }
+    ok
+}
+
+/// Records what's wrong with how a run on a destroyed device went. Work
+/// submitted after the destruction has to raise errors, whichever they are.
+fn check(
+    destroy: Destroy,
+    outcome: &Outcome,
+    lost: Option<(DeviceLostReason, String)>,
+    record: &mut Record,
+) {
+    outcome.print();
+    match lost {
+        Some((DeviceLostReason::Destroyed, message)) => println!("device lost: {message:?}"),
+        Some((reason, message)) => {
+            println!("device lost with unexpected reason {reason:?}: {message:?}");
+            record.fail(format!(
+                "device lost with unexpected reason {reason:?}: {message:?}"
+            ));
+        }
+        None => {
+            println!("device lost callback never fired");
+            record.fail("device lost callback never fired");
+        }
+    }
+    if outcome.pixels.is_some() {
+        println!("read back pixels from a destroyed device");
+        record.fail("read back pixels from a destroyed device");
+    }
+    let destroyed_before_work = matches!(destroy, Destroy::AfterUpload | Destroy::BeforeSubmit);
+    if destroyed_before_work && outcome.errors.is_empty() {
+        println!("calls on the destroyed device raised no errors");
+        record.fail("calls on the destroyed device raised no errors");
+    }
+    if destroy == Destroy::DuringMap && !outcome.errors.iter().any(|e| e.step == "readback") {
+        println!("pending map_async did not fail");
+        record.fail("pending map_async did not fail");
+    }
+}
@@ -0,0 +1,164 @@
+//! `differential [dir]`: run the scenario on every adapter of every backend
+//! and compare the results with each other instead of with the CPU
+//! reference: the read-back images, and which steps raised errors.
+//!
+//! The first adapter that read anything back is the baseline, so adapters
+//! that all fail the same way agree; it gets a `baseline` record of its
+//! own. When another adapter's image differs from the baseline's, both
+//! images and their difference are written to `dir` (default
+//! `target/differential`).
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use wgpu::Backends;
+
+use crate::cli::Args;
+use crate::harness::{self, Gpu, Outcome};
+use crate::instance;
+use crate::reference;
+use crate::report;
+use crate::results::{self, AdapterSummary, Record};
+use crate::scenario::Scenario;
+
+/// Returns `false` if any adapter disagreed with the baseline.
+pub async fn run(args: &Args, scenario: &Scenario) -> bool {
+    let dir = Path::new(args.positional(0).unwrap_or("target/differential"));
+    let instance = Arc::new(instance::new(&scenario.instance));
+    let mut runs: Vec<(Gpu, Outcome)> = Vec::new();
+    for adapter in instance.enumerate_adapters(Backends::all()) {
+        let gpu = harness::open(instance.clone(), &scenario.instance, adapter).await;
+        println!("== {}", AdapterSummary::of(&gpu).label());
+        let outcome = harness::run(&gpu, scenario).await;
+        outcome.print();
+        runs.push((gpu, outcome));
+    }
+
+    if runs.is_empty() {
+        println!("no adapters");
+        return false;
+    }
+    let base = runs
+        .iter()
+        .position(|(_, outcome)| outcome.pixels.is_some())
+        .unwrap_or(0);
+    if runs.len() < 2 {
+        println!("only one adapter, nothing to compare");
+        let record = Record::new(&runs[base].0, "differential", scenario);
+        results::skip(record, "only one adapter");
+        return true;
+    }
+
+    let (base_gpu, base_outcome) = &runs[base];
+    let base_label = AdapterSummary::of(base_gpu).label();
+    // What the others are judged by, so it agrees by definition.
+    let mut base_record = Record::new(base_gpu, "baseline", scenario);
+    let mut ok = true;
+    for (index, (gpu, outcome)) in runs.iter().enumerate() {
+        if index == base {
+            continue;
+        }
+        let label = AdapterSummary::of(gpu).label();
+        println!("== {label} vs {base_label}");
+        let mut record = Record::new(gpu, format!("vs {base_label}"), scenario);
+
+        let (base_steps, steps) = (failing_steps(base_outcome), failing_steps(outcome));
+        if base_steps != steps {
+            let reason = format!("errors in {steps:?}, baseline has them in {base_steps:?}");
+            println!("{reason}");
+            record.fail(reason);
+            record.add_errors(&outcome.errors);
+        }
+        match (base_outcome.pixels.as_deref(), outcome.pixels.as_deref()) {
+            (Some(base_pixels), Some(pixels)) => {
+                let comparison =
+                    reference::compare_within(base_pixels, pixels, reference::TOLERANCE);
+                println!("vs {base_label}: {comparison:?}");
+                record.comparison = Some(comparison);
+                record.tolerance = Some(reference::TOLERANCE);
+                if !comparison.matches() {
+                    let written = write_images(
+                        dir,
+                        scenario,
+                        (&stem(base, &base_label), base_pixels),
+                        (&stem(index, &label), pixels),
+                        base_record.artifacts.is_empty(),
+                    );
+                    match written {
+                        Ok(mut paths) => {
+                            for path in &paths {
+                                println!("wrote {path}");
+                            }
+                            if base_record.artifacts.is_empty() {
+                                base_record.artifacts.push(paths.remove(0));
+                            }
+                            record.artifacts.extend(paths);
+                        }
+                        Err(err) => println!("failed to write images to {}: {err}", dir.display()),
+                    }
+                }
+            }
+            (Some(_), None) => {
+                println!("no readback, baseline has one");
+                record.fail("no readback, baseline has one");
+            }
+            // The baseline is the first adapter with a readback, so none has one.
+            (None, _) => println!("no readback on either"),
+        }
+        ok &= results::push(record);
+    }
+    results::push(base_record) && ok
+}
+
+/// The steps that raised errors; messages name backend-specific ids, so
+/// only where the errors happened is compared.
+fn failing_steps(outcome: &Outcome) -> BTreeSet<&'static str> {
+    outcome.errors.iter().map(|err| err.step).collect()
+}
+
+/// Writes the images of both adapters and their difference, the
+/// baseline's only if `with_base`. Returns the paths written, the
+/// baseline's first.
+fn write_images(
+    dir: &Path,
+    scenario: &Scenario,
+    (base_stem, base): (&str, &[u8]),
+    (stem, pixels): (&str, &[u8]),
+    with_base: bool,
+) -> std::io::Result<Vec<String>> {
+    fs::create_dir_all(dir)?;
+    let mut images = vec![
+        (format!("{stem}.png"), pixels.to_vec()),
+        (
+            format!("{stem}-vs-{base_stem}.diff.png"),
+            report::diff(base, pixels),
+        ),
+    ];
+    if with_base {
+        images.insert(0, (format!("{base_stem}.png"), base.to_vec()));
+    }
+    let mut paths = Vec::new();
+    for (name, texels) in images {
+        let path = dir.join(name);
+        report::write_png(&path, scenario.format, &texels)?;
+        paths.push(path.display().to_string());
+    }
+    Ok(paths)
+}
+
+/// A file name for the adapter at `index`: its label in lowercase, with
+/// runs of anything but letters and digits turned into `-`. The index
+/// keeps two identical adapters apart.
+fn stem(index: usize, label: &str) -> String {
+    let mut slug = format!("{index}-");
+    for c in label.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
@@ -0,0 +1,376 @@
+//! Known failures, CTS style: with an expectations file (`--expectations=PATH`,
+//! default [`PATH`] when it exists), a run fails only on unexpected failures
+//! and unexpected passes, and lists the expectations that have gone stale.
+//!
+//! Each line reads `[bug] [ tags ] mode:case [ Failure ]`, `#` starting a
+//! comment line. `*` in `mode` or `case` matches any run of characters.
+//! Every tag has to hold for the expectation to apply:
+//!
+//! - `gl`, `vulkan`, `cpu`, ...: the adapter's backend or device type;
+//! - `key=value`: the scenario parameter `key`, as recorded in results;
+//! - hex digits: a prefix of the wgpu revision, e.g. `0a76c0fa`.
+//!
+//! Any other tag, such as a misspelled backend, is rejected on load rather
+//! than left to never apply.
+
+use std::fs;
+use std::io;
+
+use wgpu::{Backend, DeviceType};
+
+use crate::cli::Args;
+use crate::results::{Record, Status};
+
+pub const PATH: &str = "expectations.txt";
+
+#[derive(Clone, Debug)]
+pub struct Expectation {
+    /// `path:line`, how reports point at the expectation.
+    pub location: String,
+    tags: Vec<String>,
+    mode: String,
+    case: String,
+}
+
+impl Expectation {
+    fn matches(&self, record: &Record) -> bool {
+        glob(&self.mode, &record.mode)
+            && glob(&self.case, &record.case)
+            && self.tags.iter().all(|tag| tag_holds(tag, record))
+    }
+}
+
+const BACKENDS: [Backend; 6] = [
+    Backend::Empty,
+    Backend::Vulkan,
+    Backend::Metal,
+    Backend::Dx12,
+    Backend::Gl,
+    Backend::BrowserWebGpu,
+];
+
+const DEVICE_TYPES: [DeviceType; 5] = [
+    DeviceType::Other,
+    DeviceType::IntegratedGpu,
+    DeviceType::DiscreteGpu,
+    DeviceType::VirtualGpu,
+    DeviceType::Cpu,
+];
+
+/// Whether `tag` is one [`tag_holds`] can tell anything from.
+fn known_tag(tag: &str) -> bool {
+    // Named like `AdapterSummary` records them.
+    let named = |name: String| name.eq_ignore_ascii_case(tag);
+    tag.split_once('=').is_some_and(|(key, _)| !key.is_empty())
+        || BACKENDS.iter().any(|b| named(format!("{b:?}")))
+        || DEVICE_TYPES.iter().any(|d| named(format!("{d:?}")))
+        || tag.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+fn tag_holds(tag: &str, record: &Record) -> bool {
+    if let Some((key, value)) = tag.split_once('=') {
+        return record.parameters.get(key).is_some_and(|v| v == value);
+    }
+    let adapter = record.adapter.as_ref();
+    adapter.is_some_and(|a| a.backend.eq_ignore_ascii_case(tag))
+        || adapter.is_some_and(|a| a.device_type.eq_ignore_ascii_case(tag))
+        || record.wgpu_revision.starts_with(tag)
+}
+
+/// Whether `pattern`, with `*` matching any run of characters, matches all of `text`.
+fn glob(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((prefix, rest)) => {
+            let Some(text) = text.strip_prefix(prefix) else {
+                return false;
+            };
+            (0..=text.len())
+                .filter(|&i| text.is_char_boundary(i))
+                .any(|i| glob(rest, &text[i..]))
+        }
+    }
+}
+
+/// Reads the expectations `args` points at, `None` when there's no file
+/// to read. Exits with a usage error on a malformed line.
+pub fn load(args: &Args) -> Option<Vec<Expectation>> {
+    let path = args.value("expectations").unwrap_or(PATH);
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        // Only the default is optional.
+        Err(err)
+            if err.kind() == io::ErrorKind::NotFound && args.value("expectations").is_none() =>
+        {
+            return None
+        }
+        Err(err) => {
+            eprintln!("failed to read {path}: {err}");
+            std::process::exit(2);
+        }
+    };
+    let mut expectations = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let location = format!("{path}:{}", index + 1);
+        match parse(line) {
+            Some((tags, _, _)) if !tags.iter().all(|tag| known_tag(tag)) => {
+                let unknown = tags.iter().find(|tag| !known_tag(tag)).unwrap();
+                eprintln!(
+                    "{location}: unknown tag `{unknown}`, expected a backend, a device type, \
+                     key=value or a revision prefix"
+                );
+                std::process::exit(2);
+            }
+            Some((tags, mode, case)) => expectations.push(Expectation {
+                location,
+                tags,
+                mode,
+                case,
+            }),
+            None => {
+                eprintln!("{location}: expected `[bug] [ tags ] mode:case [ Failure ]`");
+                std::process::exit(2);
+            }
+        }
+    }
+    Some(expectations)
+}
+
+fn parse(line: &str) -> Option<(Vec<String>, String, String)> {
+    let line = line.strip_suffix(']')?;
+    let (line, result) = line.rsplit_once('[')?;
+    if result.trim() != "Failure" {
+        return None;
+    }
+    let (tags, query) = match line.split_once('[') {
+        Some((_bug, rest)) => {
+            let (tags, query) = rest.split_once(']')?;
+            (tags.split_whitespace().map(str::to_string).collect(), query)
+        }
+        None => (Vec::new(), line),
+    };
+    let (mode, case) = query.trim().split_once(':')?;
+    Some((tags, mode.to_string(), case.to_string()))
+}
+
+/// Marks each record with the first expectation it matches, prints the
+/// unexpected outcomes and the stale expectations.
+///
+/// Returns `false` if any case failed unexpectedly or passed unexpectedly.
+pub fn apply(expectations: &[Expectation], records: &mut [Record]) -> bool {
+    let mut ok = true;
+    for record in records.iter_mut() {
+        record.expected = expectations
+            .iter()
+            .find(|e| e.matches(record))
+            .map(|e| e.location.clone());
+        match (record.status, &record.expected) {
+            (Status::Failed, None) => {
+                println!("unexpected failure: {}: {}", record.mode, record.case);
+                ok = false;
+            }
+            (Status::Passed, Some(location)) => {
+                println!(
+                    "unexpected pass: {}: {}, expected to fail by {location}",
+                    record.mode, record.case
+                );
+                ok = false;
+            }
+            _ => {}
+        }
+    }
+    let expected = records
+        .iter()
+        .filter(|r| r.status == Status::Failed && r.expected.is_some())
+        .count();
+    if expected != 0 {
+        println!("{expected} expected failures");
+    }
+    for expectation in stale(expectations, records) {
+        println!(
+            "stale expectation at {}: everything it matched passed",
+            expectation.location
+        );
+    }
+    ok
+}
+
+/// Expectations that records marked by [`apply`] matched, none of which
+/// failed.
+fn stale<'a>(expectations: &'a [Expectation], records: &[Record]) -> Vec<&'a Expectation> {
+    expectations
+        .iter()
+        .filter(|expectation| {
+            let mut matched = records
+                .iter()
+                .filter(|r| r.expected.as_ref() == Some(&expectation.location))
+                .peekable();
+            matched.peek().is_some() && matched.all(|r| r.status != Status::Failed)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::AdapterSummary;
+
+    fn expectation(line: &str) -> Expectation {
+        let (tags, mode, case) = parse(line).unwrap();
+        Expectation {
+            location: format!("test:{line}"),
+            tags,
+            mode,
+            case,
+        }
+    }
+
+    fn record(case: &str, status: Status) -> Record {
+        Record {
+            mode: "blend".to_string(),
+            adapter: Some(AdapterSummary {
+                name: "llvmpipe".to_string(),
+                backend: "Gl".to_string(),
+                device_type: "Cpu".to_string(),
+                driver: String::new(),
+                driver_info: String::new(),
+                vendor: 0,
+                device: 0,
+            }),
+            wgpu_revision: "0a76c0fa0000000000000000000000000000000b".to_string(),
+            status,
+            ..Record::bare(case.to_string())
+        }
+    }
+
+    #[test]
+    fn parse_lines() {
+        assert_eq!(
+            parse("crbug.com/1 [ gl view_format=Rgba8UnormSrgb ] blend:add * [ Failure ]"),
+            Some((
+                vec!["gl".to_string(), "view_format=Rgba8UnormSrgb".to_string()],
+                "blend".to_string(),
+                "add *".to_string()
+            ))
+        );
+        assert_eq!(
+            parse("default:default [ Failure ]"),
+            Some((Vec::new(), "default".to_string(), "default".to_string()))
+        );
+        assert_eq!(parse("default:default"), None);
+        assert_eq!(parse("default:default [ Pass ]"), None);
+        assert_eq!(parse("default [ Failure ]"), None);
+        assert_eq!(parse("bug [ gl default:default [ Failure ]"), None);
+    }
+
+    #[test]
+    fn known_tags() {
+        for tag in [
+            "gl",
+            "Vulkan",
+            "dx12",
+            "cpu",
+            "DiscreteGpu",
+            "format=Rgba8Unorm",
+            "0a76c0fa",
+        ] {
+            assert!(known_tag(tag), "{tag}");
+        }
+        for tag in ["vulkn", "opengl", "=x", "0a76c0fz"] {
+            assert!(!known_tag(tag), "{tag}");
+        }
+    }
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob("blend", "blend"));
+        assert!(!glob("blend", "blend2"));
+        assert!(glob("*", ""));
+        assert!(glob("add *", "add Rgba8Unorm"));
+        assert!(glob("*Srgb*", "Rgba8UnormSrgb -> buffer"));
+        assert!(glob("a*b*c", "aXbYbc"));
+        assert!(!glob("a*b*c", "aXcYb"));
+        assert!(glob("é*", "éà"));
+    }
+
+    #[test]
+    fn tags_match_records() {
+        let mut record = record("add", Status::Failed);
+        record
+            .parameters
+            .insert("format".to_string(), "Rgba8Unorm".to_string());
+        for line in [
+            "blend:add [ Failure ]",
+            "[ gl ] blend:* [ Failure ]",
+            "[ cpu 0a76 format=Rgba8Unorm ] blend:add [ Failure ]",
+        ] {
+            assert!(expectation(line).matches(&record), "{line}");
+        }
+        for line in [
+            "copy:add [ Failure ]",
+            "[ vulkan ] blend:add [ Failure ]",
+            "[ gl c9a2d972 ] blend:add [ Failure ]",
+            "[ format=Bgra8Unorm ] blend:add [ Failure ]",
+        ] {
+            assert!(!expectation(line).matches(&record), "{line}");
+        }
+    }
+
+    #[test]
+    fn apply_expected_and_unexpected() {
+        let expectations = [expectation("blend:known [ Failure ]")];
+        let mut records = [
+            record("known", Status::Failed),
+            record("other", Status::Passed),
+            record("skipped", Status::Skipped),
+        ];
+        assert!(apply(&expectations, &mut records));
+        assert_eq!(
+            records[0].expected.as_deref(),
+            Some(expectations[0].location.as_str())
+        );
+        assert_eq!(records[1].expected, None);
+
+        let mut records = [record("new", Status::Failed)];
+        assert!(!apply(&expectations, &mut records));
+        assert_eq!(records[0].expected, None);
+    }
+
+    #[test]
+    fn apply_unexpected_pass_and_stale() {
+        let expectations = [
+            expectation("blend:fixed [ Failure ]"),
+            expectation("blend:* [ Failure ]"),
+        ];
+        // Matched only by the first expectation, which is now stale.
+        let mut records = [record("fixed", Status::Passed)];
+        assert!(!apply(&expectations, &mut records));
+        assert_eq!(
+            records[0].expected.as_deref(),
+            Some(expectations[0].location.as_str())
+        );
+        let stale: Vec<_> = stale(&expectations, &records)
+            .iter()
+            .map(|e| e.location.clone())
+            .collect();
+        assert_eq!(stale, [expectations[0].location.clone()]);
+
+        // The first matching expectation wins.
+        let mut records = [record("fixed", Status::Failed), record("x", Status::Failed)];
+        assert!(apply(&expectations, &mut records));
+        assert_eq!(
+            records[1].expected.as_deref(),
+            Some(expectations[1].location.as_str())
+        );
+        assert!(super::stale(&expectations, &records).is_empty());
+
+        // One failure among its matches keeps an expectation current.
+        let mut records = [record("a", Status::Passed), record("b", Status::Failed)];
+        assert!(!apply(&expectations, &mut records));
+        assert!(super::stale(&expectations, &records).is_empty());
+    }
+}
@@ -0,0 +1,730 @@
+//! `fuzz [--cases=N] [--seed=N] [--input=PATH] [--shrink=N]`: run random
+//! but valid cases on the fallback adapter and flag panics, validation
+//! errors and disagreement with the CPU reference. A case is either a
+//! [`Scenario`] or a texture round trip through `write_texture` and
+//! `copy_texture_to_buffer` with random sizes, mip and layer counts,
+//! usages, `view_formats` and copy layouts.
+//!
+//! Cases are drawn from byte strings, as libFuzzer hands them out: a
+//! failing input is shrunk within `--shrink` reruns, saved under [`DIR`],
+//! and `--input=PATH` replays it, or any other file.
+//!
+//! The same generator backs two proptest properties. `cargo test` checks
+//! that every case stays within what its capabilities allow.
+//! `cargo test -- --ignored` runs cases on the fallback adapter and checks
+//! that they pass, shrinking failing inputs the proptest way.
+
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use wgpu::{
+    BufferDescriptor, BufferUsages, Color, ColorWrites, CommandEncoderDescriptor, DownlevelFlags,
+    Extent3d, Features, FilterMode, ImageCopyBuffer, ImageCopyTexture, ImageDataLayout, LoadOp,
+    Origin3d, StoreOp, TextureAspect, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureUsages, COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+
+use crate::blend;
+use crate::cli::Args;
+use crate::compilation;
+use crate::harness::{self, step, Gpu, Outcome};
+use crate::instance;
+use crate::reference;
+use crate::results::{self, Record};
+use crate::sampling;
+use crate::scenario::{Compilation, Layout, Read, Rect, Sampling, Scenario, TextureBinding, SIZE};
+
+pub const DIR: &str = "target/fuzz";
+
+const CASES: usize = 100;
+/// Reruns a failing input may take to shrink.
+const SHRINK: usize = 200;
+/// Length of the random inputs; cases read zeros past the end.
+const INPUT_LEN: usize = 64;
+
+/// Formats the reference handles, in both memory orders.
+const FORMATS: [TextureFormat; 4] = [
+    TextureFormat::Rgba8Unorm,
+    TextureFormat::Rgba8UnormSrgb,
+    TextureFormat::Bgra8Unorm,
+    TextureFormat::Bgra8UnormSrgb,
+];
+
+const CLEAR_COLORS: [Color; 4] = [
+    Color::TRANSPARENT,
+    Color::BLACK,
+    Color::WHITE,
+    Color {
+        r: 0.2,
+        g: 0.5,
+        b: 0.8,
+        a: 0.6,
+    },
+];
+
+/// Largest texture side and array layer count of copy cases.
+const MAX_SIDE: u32 = 64;
+const MAX_LAYERS: u32 = 4;
+
+/// The byte string a case is drawn from. Past the end it reads zeros, and
+/// a zero always picks the first and simplest option, so shrinking heads
+/// for shorter inputs and smaller bytes.
+struct Source<'a> {
+    bytes: &'a [u8],
+}
+
+impl Source<'_> {
+    fn byte(&mut self) -> u8 {
+        match self.bytes.split_first() {
+            Some((&byte, rest)) => {
+                self.bytes = rest;
+                byte
+            }
+            None => 0,
+        }
+    }
+
+    fn flag(&mut self) -> bool {
+        self.byte() & 1 == 1
+    }
+
+    /// A flag that is set about one time in eight.
+    fn rarely(&mut self) -> bool {
+        self.byte() >= 0xe0
+    }
+
+    fn below(&mut self, n: u32) -> u32 {
+        u32::from(u16::from_le_bytes([self.byte(), self.byte()])) % n
+    }
+
+    fn choose<T: Clone>(&mut self, options: &[T]) -> T {
+        options[self.below(options.len() as u32) as usize].clone()
+    }
+}
+
+/// What the device allows beyond the WebGPU baseline, so generated cases
+/// stay valid on it.
+#[derive(Clone, Copy, Debug)]
+struct Capabilities {
+    view_formats: bool,
+    dual_source: bool,
+}
+
+impl Capabilities {
+    fn of(gpu: &Gpu) -> Self {
+        Capabilities {
+            view_formats: gpu
+                .adapter
+                .get_downlevel_capabilities()
+                .flags
+                .contains(DownlevelFlags::VIEW_FORMATS),
+            dual_source: gpu
+                .device
+                .features()
+                .contains(Features::DUAL_SOURCE_BLENDING),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Case {
+    Render(Scenario),
+    Copy(CopyCase),
+}
+
+impl Case {
+    fn generate(bytes: &[u8], capabilities: Capabilities) -> Self {
+        let mut source = Source { bytes };
+        if source.flag() {
+            Case::Copy(CopyCase::generate(&mut source, capabilities))
+        } else {
+            Case::Render(scenario(&mut source, capabilities))
+        }
+    }
+}
+
+/// The sRGB counterpart of `format`, or the linear one of an sRGB format.
+fn toggle_srgb(format: TextureFormat) -> TextureFormat {
+    if format.is_srgb() {
+        format.remove_srgb_suffix()
+    } else {
+        format.add_srgb_suffix()
+    }
+}
+
+fn scenario(source: &mut Source, capabilities: Capabilities) -> Scenario {
+    let format = source.choose(&FORMATS);
+    let mut reinterpret =
+        || (capabilities.view_formats && source.flag()).then(|| toggle_srgb(format));
+    let view_format = reinterpret().unwrap_or(format);
+    let source_view_format = reinterpret();
+    let read = if source.flag() {
+        Read::Sample(Sampling {
+            filter: source.choose(&[FilterMode::Nearest, FilterMode::Linear]),
+            explicit_level: source.flag(),
+            offset: source.choose(&sampling::OFFSETS),
+        })
+    } else {
+        Read::Load
+    };
+    let layout = if source.flag() {
+        Layout::Explicit(TextureBinding::default())
+    } else {
+        Layout::Auto
+    };
+    let mut scenario = Scenario {
+        format,
+        view_format,
+        source_view_format,
+        read,
+        layout,
+        ..Scenario::default()
+    };
+    if source.flag() {
+        // Dual-source blending needs the feature and the `textureLoad` shader.
+        let presets: Vec<_> = blend::presets()
+            .into_iter()
+            .map(|(_, blend)| Some(blend))
+            .filter(|&blend| {
+                let scenario = Scenario {
+                    blend,
+                    ..scenario.clone()
+                };
+                !scenario.dual_source() || (capabilities.dual_source && read == Read::Load)
+            })
+            .collect();
+        scenario.blend = source.choose(&presets);
+    }
+    scenario.load = if source.flag() {
+        LoadOp::Load
+    } else {
+        LoadOp::Clear(source.choose(&CLEAR_COLORS))
+    };
+    if source.rarely() {
+        scenario.store = StoreOp::Discard;
+    }
+    scenario.draw = !source.rarely();
+    if source.flag() {
+        scenario.write_mask = ColorWrites::from_bits_truncate(source.below(16));
+    }
+    scenario.viewport = source.flag().then(|| rect(source));
+    scenario.scissor = source.flag().then(|| rect(source));
+    // Constants only exist in `SHADER_OVERRIDE`; anything else rejects them.
+    let constants = if read == Read::Load && !scenario.dual_source() && source.flag() {
+        source.choose(compilation::CONSTANTS)
+    } else {
+        &[]
+    };
+    scenario.compilation = Compilation {
+        constants: constants.iter().map(|&(k, v)| (k.to_string(), v)).collect(),
+        zero_initialize_workgroup_memory: !source.flag(),
+        vertex_pulling_transform: source.flag(),
+    };
+    scenario
+}
+
+/// A non-empty rectangle inside `tex01`.
+fn rect(source: &mut Source) -> Rect {
+    let (x, y) = (source.below(SIZE), source.below(SIZE));
+    Rect {
+        x,
+        y,
+        width: 1 + source.below(SIZE - x),
+        height: 1 + source.below(SIZE - y),
+    }
+}
+
+/// How far a scenario may drift from the reference, by what it exercises.
+fn tolerance(scenario: &Scenario) -> u8 {
+    let mut tolerance = reference::TOLERANCE;
+    if scenario.blend.is_some() {
+        tolerance = tolerance.max(blend::TOLERANCE);
+    }
+    if let Read::Sample(Sampling {
+        filter: FilterMode::Linear,
+        ..
+    }) = scenario.read
+    {
+        tolerance = tolerance.max(sampling::TOLERANCE);
+    }
+    tolerance
+}
+
+/// A region of one mip level written with `write_texture` and read back
+/// with `copy_texture_to_buffer`, both through `layout`.
+#[derive(Clone, Debug)]
+struct CopyCase {
+    format: TextureFormat,
+    size: Extent3d,
+    mip_level_count: u32,
+    usage: TextureUsages,
+    view_formats: Vec<TextureFormat>,
+    mip_level: u32,
+    origin: Origin3d,
+    extent: Extent3d,
+    layout: ImageDataLayout,
+}
+
+impl CopyCase {
+    fn generate(source: &mut Source, capabilities: Capabilities) -> Self {
+        let format = source.choose(&FORMATS);
+        let size = Extent3d {
+            width: 1 + source.below(MAX_SIDE),
+            height: 1 + source.below(MAX_SIDE),
+            depth_or_array_layers: 1 + source.below(MAX_LAYERS),
+        };
+        let mip_level_count = 1 + source.below(size.max_mips(TextureDimension::D2));
+        let mut usage = TextureUsages::COPY_SRC | TextureUsages::COPY_DST;
+        if source.flag() {
+            usage |= TextureUsages::TEXTURE_BINDING;
+        }
+        if source.flag() {
+            usage |= TextureUsages::RENDER_ATTACHMENT;
+        }
+        let view_formats = if capabilities.view_formats && source.flag() {
+            vec![toggle_srgb(format)]
+        } else {
+            Vec::new()
+        };
+
+        let mip_level = source.below(mip_level_count);
+        let mip = size.mip_level_size(mip_level, TextureDimension::D2);
+        let origin = Origin3d {
+            x: source.below(mip.width),
+            y: source.below(mip.height),
+            z: source.below(mip.depth_or_array_layers),
+        };
+        let extent = Extent3d {
+            width: 1 + source.below(mip.width - origin.x),
+            height: 1 + source.below(mip.height - origin.y),
+            depth_or_array_layers: 1 + source.below(mip.depth_or_array_layers - origin.z),
+        };
+        let row = extent.width * 4;
+        let aligned = row.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+        let layout = ImageDataLayout {
+            offset: 4 * u64::from(source.below(64)),
+            bytes_per_row: Some(aligned + COPY_BYTES_PER_ROW_ALIGNMENT * source.below(2)),
+            rows_per_image: Some(extent.height + source.below(3)),
+        };
+        CopyCase {
+            format,
+            size,
+            mip_level_count,
+            usage,
+            view_formats,
+            mip_level,
+            origin,
+            extent,
+            layout,
+        }
+    }
+
+    /// Bytes of a buffer holding the region in `layout`.
+    fn buffer_size(&self) -> u64 {
+        let (bytes_per_row, rows_per_image) = self.strides();
+        self.layout.offset
+            + bytes_per_row * rows_per_image * u64::from(self.extent.depth_or_array_layers)
+    }
+
+    fn strides(&self) -> (u64, u64) {
+        (
+            u64::from(self.layout.bytes_per_row.unwrap()),
+            u64::from(self.layout.rows_per_image.unwrap()),
+        )
+    }
+
+    /// The region's texels in `data`, which is laid out like the buffers.
+    fn texels(&self, data: &[u8]) -> Vec<u8> {
+        let (bytes_per_row, rows_per_image) = self.strides();
+        let row = u64::from(self.extent.width) * 4;
+        let mut texels = Vec::new();
+        for z in 0..u64::from(self.extent.depth_or_array_layers) {
+            for y in 0..u64::from(self.extent.height) {
+                let start =
+                    self.layout.offset + z * bytes_per_row * rows_per_image + y * bytes_per_row;
+                texels.extend_from_slice(&data[start as usize..(start + row) as usize]);
+            }
+        }
+        texels
+    }
+
+    async fn run(&self, gpu: &Gpu) -> Outcome {
+        let Gpu { device, queue, .. } = gpu;
+        let mut errors = Vec::new();
+        // Period 251 so neighbouring rows and layers differ.
+        let data: Vec<u8> = (0..self.buffer_size()).map(|i| (i % 251) as u8).collect();
+        let texture = step(device, &mut errors, "create_texture", || {
+            device.create_texture(&TextureDescriptor {
+                label: None,
+                size: self.size,
+                mip_level_count: self.mip_level_count,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: self.format,
+                usage: self.usage,
+                view_formats: &self.view_formats,
+            })
+        })
+        .await;
+        let copy = ImageCopyTexture {
+            texture: &texture,
+            mip_level: self.mip_level,
+            origin: self.origin,
+            aspect: TextureAspect::All,
+        };
+        step(device, &mut errors, "write_texture", || {
+            queue.write_texture(copy, &data, self.layout, self.extent)
+        })
+        .await;
+        let readback = device.create_buffer(&BufferDescriptor {
+            label: None,
+            size: self.buffer_size(),
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        step(device, &mut errors, "copy", || {
+            let mut cmd_enc =
+                device.create_command_encoder(&CommandEncoderDescriptor { label: None });
+            cmd_enc.copy_texture_to_buffer(
+                copy,
+                ImageCopyBuffer {
+                    buffer: &readback,
+                    layout: self.layout,
+                },
+                self.extent,
+            );
+            queue.submit([cmd_enc.finish()]);
+        })
+        .await;
+        let copy_ok = !errors.iter().any(|err| err.step == "copy");
+        let pixels = copy_ok
+            .then(|| harness::read_buffer(device, &readback))
+            .flatten()
+            .map(|data| self.texels(&data));
+        Outcome {
+            errors,
+            pixels,
+            ..Default::default()
+        }
+    }
+}
+
+/// The fallback device cases run on, and what it allows them.
+struct Fuzzer {
+    gpu: Gpu,
+    capabilities: Capabilities,
+}
+
+impl Fuzzer {
+    fn open(settings: &instance::Settings) -> Self {
+        let instance = instance::new(settings);
+        let adapter = match pollster::block_on(harness::request_fallback_adapter(&instance)) {
+            Some(adapter) => adapter,
+            None => {
+                println!("no fallback adapter, fuzzing on the default one");
+                pollster::block_on(harness::request_adapter(&instance))
+            }
+        };
+        let gpu = pollster::block_on(harness::open(instance, settings, adapter));
+        let capabilities = Capabilities::of(&gpu);
+        Fuzzer { gpu, capabilities }
+    }
+
+    /// Runs the case `bytes` describe. A panic is recorded as a failure
+    /// and replaces the device, which may be left in any state.
+    fn check(&mut self, bytes: &[u8]) -> (Case, Record) {
+        let case = Case::generate(bytes, self.capabilities);
+        let name = format!("{} {}", kind(&case), hex(bytes));
+        let (mut record, tolerance, expected) = match &case {
+            Case::Render(scenario) => (
+                Record::new(&self.gpu, name, scenario),
+                tolerance(scenario),
+                reference::render(scenario),
+            ),
+            Case::Copy(copy) => {
+                let scenario = Scenario {
+                    format: copy.format,
+                    view_format: copy.format,
+                    ..Scenario::default()
+                };
+                let mut record = Record::new(&self.gpu, name, &scenario);
+                record
+                    .parameters
+                    .insert("copy".to_string(), format!("{copy:?}"));
+                let data: Vec<u8> = (0..copy.buffer_size()).map(|i| (i % 251) as u8).collect();
+                (record, 0, copy.texels(&data))
+            }
+        };
+        let gpu = &self.gpu;
+        let result = panic::catch_unwind(AssertUnwindSafe(|| match &case {
+            Case::Render(scenario) => pollster::block_on(harness::run(gpu, scenario)),
+            Case::Copy(copy) => pollster::block_on(copy.run(gpu)),
+        }));
+        match result {
+            Ok(outcome) => {
+                record.add_outcome(&outcome);
+                match &outcome.pixels {
+                    Some(pixels) => {
+                        record.comparison =
+                            Some(reference::compare_within(&expected, pixels, tolerance));
+                        record.tolerance = Some(tolerance);
+                    }
+                    None => record.fail("no readback"),
+                }
+            }
+            Err(payload) => {
+                record.fail(format!("panicked: {}", harness::panic_message(&*payload)));
+                let settings = self.gpu.settings.clone();
+                *self = Fuzzer::open(&settings);
+            }
+        }
+        (case, record)
+    }
+}
+
+fn kind(case: &Case) -> &'static str {
+    match case {
+        Case::Render(_) => "render",
+        Case::Copy(_) => "copy",
+    }
+}
+
+/// `bytes` in hex, without the trailing zeros that read the same as none.
+fn hex(bytes: &[u8]) -> String {
+    let end = bytes.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+    if end == 0 {
+        return "00".to_string();
+    }
+    bytes[..end].iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// What kind of failure a record shows, so shrinking doesn't wander off
+/// to a different bug.
+fn signature(record: &Record) -> (Vec<String>, bool, bool) {
+    (
+        record.errors.iter().map(|err| err.step.clone()).collect(),
+        record.comparison.is_some_and(|c| !c.matches()),
+        record.failures.iter().any(|f| f.starts_with("panicked")),
+    )
+}
+
+fn failed(record: &Record) -> bool {
+    !record.reasons().is_empty()
+}
+
+/// Cuts short and lowers bytes of a failing input while it keeps failing
+/// the same way, within `budget` reruns.
+fn shrink(fuzzer: &mut Fuzzer, bytes: &[u8], record: &Record, budget: usize) -> Vec<u8> {
+    let target = signature(record);
+    let mut runs = 0;
+    let mut still_fails = |candidate: &[u8]| {
+        if runs >= budget {
+            return false;
+        }
+        runs += 1;
+        let (_, record) = fuzzer.check(candidate);
+        failed(&record) && signature(&record) == target
+    };
+    let mut best = bytes[..bytes.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1)].to_vec();
+    while !best.is_empty() && still_fails(&best[..best.len() / 2]) {
+        best.truncate(best.len() / 2);
+    }
+    loop {
+        let mut progress = false;
+        for i in 0..best.len() {
+            // Zero first; keeping the low bit keeps a flag, halving shrinks a number.
+            for smaller in [0, best[i] & 1, best[i] / 2] {
+                if smaller >= best[i] {
+                    continue;
+                }
+                let mut candidate = best.clone();
+                candidate[i] = smaller;
+                if still_fails(&candidate) {
+                    best = candidate;
+                    progress = true;
+                    break;
+                }
+            }
+        }
+        if !progress {
+            break;
+        }
+    }
+    best
+}
+
+/// `INPUT_LEN` bytes for case `index` of the run seeded with `seed`, from
+/// SplitMix64.
+fn random_input(seed: u64, index: u64) -> Vec<u8> {
+    let mut state = seed ^ index.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    let mut next = || {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    };
+    (0..INPUT_LEN / 8)
+        .flat_map(|_| next().to_le_bytes())
+        .collect()
+}
+
+/// Returns `false` if any case failed.
+/// Cases run on an instance with `scenario.instance`.
+pub fn run(args: &Args, scenario: &Scenario) -> bool {
+    let mut fuzzer = Fuzzer::open(&scenario.instance);
+    println!("{:?}", fuzzer.gpu.adapter.get_info());
+    println!("{:?}", fuzzer.capabilities);
+
+    if let Some(path) = args.value("input") {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                eprintln!("failed to read {path}: {err}");
+                std::process::exit(2);
+            }
+        };
+        let (case, record) = fuzzer.check(&bytes);
+        println!("== {}", record.case);
+        println!("{case:#?}");
+        for reason in record.reasons() {
+            println!("{reason}");
+        }
+        return results::push(record);
+    }
+
+    let count = |s: &str| s.parse().ok().filter(|&n| n > 0);
+    let cases = args.parse("cases", count).unwrap_or(CASES);
+    let budget = args.parse("shrink", |s| s.parse().ok()).unwrap_or(SHRINK);
+    let seed = args.parse("seed", |s| s.parse().ok()).unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64)
+    });
+    println!("{cases} cases, --seed={seed}");
+
+    let mut ok = true;
+    let mut failures = 0;
+    for index in 0..cases as u64 {
+        let bytes = random_input(seed, index);
+        let (_, record) = fuzzer.check(&bytes);
+        if !failed(&record) {
+            continue;
+        }
+        failures += 1;
+        println!("== case {index} failed, shrinking");
+        let bytes = shrink(&mut fuzzer, &bytes, &record, budget);
+        let (case, mut record) = fuzzer.check(&bytes);
+        println!("== {}", record.case);
+        println!("{case:#?}");
+        for reason in record.reasons() {
+            println!("{reason}");
+        }
+        let path = format!("{DIR}/{}-{}", kind(&case), hex(&bytes));
+        match fs::create_dir_all(DIR).and_then(|()| fs::write(&path, &bytes)) {
+            Ok(()) => {
+                println!("saved as {path}, replay with --input={path}");
+                record.artifacts.push(path);
+            }
+            Err(err) => println!("failed to save {path}: {err}"),
+        }
+        ok &= results::push(record);
+    }
+    println!("{failures} of {cases} cases failed");
+    ok
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn input() -> impl Strategy<Value = Vec<u8>> {
+        vec(any::<u8>(), 0..=INPUT_LEN)
+    }
+
+    fn capabilities() -> impl Strategy<Value = Capabilities> {
+        (any::<bool>(), any::<bool>()).prop_map(|(view_formats, dual_source)| Capabilities {
+            view_formats,
+            dual_source,
+        })
+    }
+
+    /// Opened on first use and never dropped, like the device of a run.
+    static FUZZER: Mutex<Option<Fuzzer>> = Mutex::new(None);
+
+    proptest! {
+        #[test]
+        fn render_cases_stay_valid(bytes in input(), capabilities in capabilities()) {
+            let Case::Render(scenario) = Case::generate(&[&[0][..], &bytes].concat(), capabilities)
+            else {
+                unreachable!("an even first byte draws a render case");
+            };
+            if !capabilities.view_formats {
+                prop_assert_eq!(scenario.view_format, scenario.format);
+                prop_assert_eq!(scenario.source_view_format, None);
+            }
+            if scenario.dual_source() {
+                prop_assert!(capabilities.dual_source);
+                prop_assert_eq!(&scenario.read, &Read::Load);
+            }
+            if !scenario.compilation.constants.is_empty() {
+                prop_assert!(scenario.read == Read::Load && !scenario.dual_source());
+            }
+            for rect in scenario.viewport.iter().chain(&scenario.scissor) {
+                prop_assert!(rect.width > 0 && rect.x + rect.width <= SIZE);
+                prop_assert!(rect.height > 0 && rect.y + rect.height <= SIZE);
+            }
+            prop_assert_eq!(reference::render(&scenario).len(), (SIZE * SIZE * 4) as usize);
+        }
+
+        #[test]
+        fn copy_cases_stay_valid(bytes in input(), capabilities in capabilities()) {
+            let Case::Copy(copy) = Case::generate(&[&[1][..], &bytes].concat(), capabilities)
+            else {
+                unreachable!("an odd first byte draws a copy case");
+            };
+            if !capabilities.view_formats {
+                prop_assert!(copy.view_formats.is_empty());
+            }
+            prop_assert!(copy.mip_level < copy.mip_level_count);
+            let mip = copy.size.mip_level_size(copy.mip_level, TextureDimension::D2);
+            prop_assert!(copy.extent.width > 0 && copy.origin.x + copy.extent.width <= mip.width);
+            prop_assert!(copy.extent.height > 0 && copy.origin.y + copy.extent.height <= mip.height);
+            prop_assert!(
+                copy.extent.depth_or_array_layers > 0
+                    && copy.origin.z + copy.extent.depth_or_array_layers
+                        <= mip.depth_or_array_layers
+            );
+            let (bytes_per_row, rows_per_image) = copy.strides();
+            prop_assert_eq!(bytes_per_row % u64::from(COPY_BYTES_PER_ROW_ALIGNMENT), 0);
+            prop_assert!(bytes_per_row >= u64::from(copy.extent.width) * 4);
+            prop_assert!(rows_per_image >= u64::from(copy.extent.height));
+            prop_assert_eq!(copy.layout.offset % 4, 0);
+            let data = vec![0; copy.buffer_size() as usize];
+            let extent = copy.extent;
+            prop_assert_eq!(
+                copy.texels(&data).len(),
+                (extent.width * extent.height * extent.depth_or_array_layers * 4) as usize
+            );
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        #[ignore = "needs an adapter; run with `cargo test -- --ignored`"]
+        fn cases_pass_on_the_fallback_adapter(bytes in input()) {
+            let mut fuzzer = FUZZER.lock().unwrap_or_else(|err| err.into_inner());
+            let (_, record) = fuzzer.get_or_insert_with(|| Fuzzer::open(&instance::configured())).check(&bytes);
+            let reasons = record.reasons();
+            prop_assert!(reasons.is_empty(), "{}", reasons.join("\n"));
+        }
+    }
+}
@@ -0,0 +1,785 @@
+//! Device setup and the repro itself, parameterized by a [`Scenario`].
+
+use std::any::Any;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use wgpu::{
+    Adapter, AddressMode, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, BufferBindingType, BufferDescriptor, BufferUsages,
+    ColorTargetState, CommandEncoderDescriptor, Device, DeviceDescriptor, ErrorFilter, Extent3d,
+    Features, FilterMode, FragmentState, ImageCopyBuffer, Instance, InstanceFlags, Limits, LoadOp,
+    MapMode, MultisampleState, Operations, PipelineCache, PipelineCompilationOptions,
+    PipelineLayoutDescriptor, PrimitiveState, QuerySet, Queue, RenderPassColorAttachment,
+    RenderPassDescriptor, RenderPassTimestampWrites, RenderPipelineDescriptor,
+    RequestAdapterOptions, SamplerBindingType, SamplerDescriptor, ShaderModuleDescriptor,
+    ShaderSource, ShaderStages, SubmissionIndex, Texture, TextureAspect, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureUsages, TextureViewDescriptor, VertexState,
+};
+
+use crate::instance;
+use crate::logging;
+use crate::nonblocking;
+use crate::reference;
+use crate::scenario::{Destroy, Layout, Read, Scenario, SIZE};
+use crate::shaders;
+use crate::DATA;
+
+/// Features the scenarios use when the adapter has them.
+const OPTIONAL_FEATURES: Features = Features::DUAL_SOURCE_BLENDING
+    .union(Features::PIPELINE_CACHE)
+    .union(Features::TIMESTAMP_QUERY)
+    .union(Features::PIPELINE_STATISTICS_QUERY);
+
+/// `bytes_per_row` of `DATA` and of the readback buffer.
+pub const ROW_PITCH: u32 = 256;
+
+pub struct Gpu {
+    /// Shared by the devices of adapters enumerated together.
+    pub instance: Arc<Instance>,
+    /// What `instance` was created with.
+    pub settings: instance::Settings,
+    pub adapter: Adapter,
+    pub device: Device,
+    pub queue: Queue,
+}
+
+/// Opens the default adapter of an instance with `scenario.instance`.
+pub async fn init(scenario: &Scenario) -> Gpu {
+    let instance = instance::new(&scenario.instance);
+    let adapter = request_adapter(&instance).await;
+    open(instance, &scenario.instance, adapter).await
+}
+
+pub async fn request_adapter(instance: &Instance) -> Adapter {
+    instance
+        .request_adapter(&RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::None,
+            force_fallback_adapter: false,
+            compatible_surface: None,
+        })
+        .await
+        .unwrap()
+}
+
+/// The platform's software adapter, such as WARP or llvmpipe, if it has one.
+pub async fn request_fallback_adapter(instance: &Instance) -> Option<Adapter> {
+    instance
+        .request_adapter(&RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::None,
+            force_fallback_adapter: true,
+            compatible_surface: None,
+        })
+        .await
+}
+
+/// Creates the device the scenarios run on. `settings` are what
+/// `instance` was created with.
+pub async fn open(
+    instance: impl Into<Arc<Instance>>,
+    settings: &instance::Settings,
+    adapter: Adapter,
+) -> Gpu {
+    open_traced(instance, settings, adapter, None).await
+}
+
+/// [`open`], recording an API trace into `trace` when built with the
+/// `trace` feature.
+pub async fn open_traced(
+    instance: impl Into<Arc<Instance>>,
+    settings: &instance::Settings,
+    adapter: Adapter,
+    trace: Option<&Path>,
+) -> Gpu {
+    let backend = adapter.get_info().backend;
+    if settings.flags.contains(InstanceFlags::GPU_BASED_VALIDATION)
+        && !instance::supports_gpu_based_validation(backend)
+    {
+        println!("gpu-based validation is not supported on {backend:?}, running without it");
+    }
+    let (device, queue) = adapter
+        .request_device(
+            &DeviceDescriptor {
+                label: None,
+                required_features: adapter.features() & OPTIONAL_FEATURES,
+                required_limits: Limits::default(),
+            },
+            trace,
+        )
+        .await
+        .unwrap();
+    device.on_uncaptured_error(Box::new(|err| println!("ERROR: {err:?}")));
+    Gpu {
+        instance: instance.into(),
+        settings: settings.clone(),
+        adapter,
+        device,
+        queue,
+    }
+}
+
+/// A validation error and the step of the scenario that raised it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StepError {
+    pub step: &'static str,
+    pub message: String,
+    /// What the step logged before it failed, see [`logging`].
+    pub logs: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Outcome {
+    pub errors: Vec<StepError>,
+    /// Tightly packed `SIZE`x`SIZE` texels of `tex01`, if they could be read back.
+    pub pixels: Option<Vec<u8>>,
+    /// CPU time of the shader, pipeline, encoding, submit and readback
+    /// stages, in the order they ran.
+    pub timings: Vec<(&'static str, Duration)>,
+}
+
+impl Outcome {
+    pub fn print(&self) {
+        for err in &self.errors {
+            println!("ERROR in {}: {}", err.step, err.message);
+        }
+        match &self.pixels {
+            Some(pixels) => println!("read back {} bytes", pixels.len()),
+            None => println!("no readback"),
+        }
+    }
+}
+
+/// Runs `f` inside a validation error scope, recording what it raised
+/// along with what it logged.
+pub async fn step<T>(
+    device: &Device,
+    errors: &mut Vec<StepError>,
+    name: &'static str,
+    f: impl FnOnce() -> T,
+) -> T {
+    device.push_error_scope(ErrorFilter::Validation);
+    let (out, logs) = logging::capture(f);
+    if let Some(err) = device.pop_error_scope().await {
+        errors.push(StepError {
+            step: name,
+            message: err.to_string(),
+            logs,
+        });
+    }
+    out
+}
+
+/// What a mode can hand to [`run_with`] on top of the scenario.
+#[derive(Clone, Copy, Default)]
+pub struct Extras<'a> {
+    /// Used to create the render pipeline.
+    pub cache: Option<&'a PipelineCache>,
+    /// Written at the beginning and end of the render pass, at indices 0 and 1.
+    pub timestamps: Option<&'a QuerySet>,
+    /// Counts the samples the draw passes, at index 0.
+    pub occlusion: Option<&'a QuerySet>,
+    /// Pipeline statistics of the draw, at index 0.
+    pub statistics: Option<&'a QuerySet>,
+    /// Called with each step's name once it has finished.
+    pub after_step: Option<&'a (dyn Fn(&'static str) + Sync)>,
+    /// Await GPU work through callbacks instead of blocking in `poll`; needs
+    /// a [`nonblocking::Poller`] running to ever finish.
+    pub nonblocking: bool,
+}
+
+impl Extras<'_> {
+    fn finished(&self, step: &'static str) {
+        if let Some(after_step) = self.after_step {
+            after_step(step);
+        }
+    }
+}
+
+pub async fn run(gpu: &Gpu, scenario: &Scenario) -> Outcome {
+    run_with(gpu, scenario, Extras::default()).await
+}
+
+/// [`run`] with `extras` wired into the pipeline and the render pass.
+pub async fn run_with(gpu: &Gpu, scenario: &Scenario, extras: Extras<'_>) -> Outcome {
+    let Gpu { device, queue, .. } = gpu;
+    let mut errors = Vec::new();
+    let mut timings = Vec::new();
+    let size = Extent3d {
+        width: SIZE,
+        height: SIZE,
+        depth_or_array_layers: 1,
+    };
+    // format=rgba8unorm
+    // viewFormat=rgba8unorm-srgb
+    // sampleCount=1
+    let (tex01, tex11) = step(device, &mut errors, "create_textures", || {
+        let tex01 = device.create_texture(&TextureDescriptor {
+            label: None,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: scenario.format,
+            usage: match scenario.load {
+                // Needs the destination pattern uploaded first.
+                LoadOp::Load => TextureUsages::COPY_DST,
+                LoadOp::Clear(_) => TextureUsages::empty(),
+            } | TextureUsages::COPY_SRC
+                | TextureUsages::RENDER_ATTACHMENT,
+            view_formats: view_formats(scenario.format, Some(&scenario.view_format)),
+        });
+        let tex11 = device.create_texture(&TextureDescriptor {
+            label: None,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: scenario.format,
+            usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
+            view_formats: view_formats(scenario.format, scenario.source_view_format.as_ref()),
+        });
+        (tex01, tex11)
+    })
+    .await;
+    extras.finished("create_textures");
+
+    step(device, &mut errors, "upload", || {
+        if extras.nonblocking {
+            submit_upload(gpu, &tex11, &DATA);
+            if scenario.load == LoadOp::Load {
+                submit_upload(gpu, &tex01, &pad_rows(&reference::destination()));
+            }
+        } else {
+            upload(gpu, &tex11, &DATA);
+            if scenario.load == LoadOp::Load {
+                upload(gpu, &tex01, &pad_rows(&reference::destination()));
+            }
+        }
+    })
+    .await;
+    if extras.nonblocking {
+        nonblocking::work_done(queue).await;
+    }
+    extras.finished("upload");
+    if scenario.destroy == Some(Destroy::AfterUpload) {
+        device.destroy();
+    }
+
+    let (view01, view11) = step(device, &mut errors, "create_views", || {
+        let view01 = tex01.create_view(&TextureViewDescriptor {
+            label: None,
+            format: Some(scenario.view_format),
+            dimension: None,
+            aspect: TextureAspect::All,
+            base_mip_level: 0,
+            mip_level_count: None,
+            base_array_layer: 0,
+            array_layer_count: None,
+        });
+        let view11 = tex11.create_view(&TextureViewDescriptor {
+            label: None,
+            format: scenario.source_view_format,
+            dimension: None,
+            aspect: TextureAspect::All,
+            base_mip_level: 0,
+            mip_level_count: None,
+            base_array_layer: 0,
+            array_layer_count: None,
+        });
+        (view01, view11)
+    })
+    .await;
+    extras.finished("create_views");
+
+    let (shader01, shader11) = step(device, &mut errors, "create_shader_modules", || {
+        timed(&mut timings, "create_shader_modules", || {
+            let shader01 = device.create_shader_module(ShaderModuleDescriptor {
+                label: None,
+                source: ShaderSource::Wgsl(Cow::Borrowed(shaders::SHADER01)),
+            });
+            let shader11 = device.create_shader_module(ShaderModuleDescriptor {
+                label: None,
+                source: ShaderSource::Wgsl(Cow::Borrowed(match scenario.read {
+                    Read::Load if scenario.dual_source() => shaders::SHADER_DUAL_SOURCE,
+                    Read::Load if scenario.overridden() => shaders::SHADER_OVERRIDE,
+                    Read::Load => shaders::SHADER11,
+                    Read::Sample(_) => shaders::SHADER_SAMPLE,
+                })),
+            });
+            (shader01, shader11)
+        })
+    })
+    .await;
+    extras.finished("create_shader_modules");
+    let fs_entry_point = match scenario.read {
+        Read::Load => "main",
+        Read::Sample(sampling) if sampling.explicit_level => "fs_sample_level",
+        Read::Sample(_) => "fs_sample",
+    };
+
+    let sampler = match scenario.read {
+        Read::Load => None,
+        Read::Sample(sampling) => Some(
+            step(device, &mut errors, "create_sampler", || {
+                let sampler = device.create_sampler(&SamplerDescriptor {
+                    label: None,
+                    address_mode_u: AddressMode::ClampToEdge,
+                    address_mode_v: AddressMode::ClampToEdge,
+                    address_mode_w: AddressMode::ClampToEdge,
+                    mag_filter: sampling.filter,
+                    min_filter: sampling.filter,
+                    mipmap_filter: FilterMode::Nearest,
+                    lod_min_clamp: 0.0,
+                    lod_max_clamp: 32.0,
+                    compare: None,
+                    anisotropy_clamp: 1,
+                    border_color: None,
+                });
+                let offset = device.create_buffer(&BufferDescriptor {
+                    label: None,
+                    size: 16,
+                    usage: BufferUsages::UNIFORM,
+                    mapped_at_creation: true,
+                });
+                offset.slice(..).get_mapped_range_mut()[..8].copy_from_slice(
+                    &[
+                        sampling.offset[0].to_le_bytes(),
+                        sampling.offset[1].to_le_bytes(),
+                    ]
+                    .concat(),
+                );
+                offset.unmap();
+                (sampler, offset)
+            })
+            .await,
+        ),
+    };
+    if sampler.is_some() {
+        extras.finished("create_sampler");
+    }
+
+    let explicit = match scenario.layout {
+        Layout::Auto => None,
+        Layout::Explicit(binding) => Some(
+            step(device, &mut errors, "create_layouts", || {
+                let mut entries = vec![BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: binding.sample_type,
+                        view_dimension: binding.view_dimension,
+                        multisampled: binding.multisampled,
+                    },
+                    count: None,
+                }];
+                if let Read::Sample(sampling) = scenario.read {
+                    entries.push(BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Sampler(match sampling.filter {
+                            FilterMode::Nearest => SamplerBindingType::NonFiltering,
+                            FilterMode::Linear => SamplerBindingType::Filtering,
+                        }),
+                        count: None,
+                    });
+                    entries.push(BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    });
+                }
+                let bind_group_layout =
+                    device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                        label: None,
+                        entries: &entries,
+                    });
+                let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                    label: None,
+                    bind_group_layouts: &[&bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+                (bind_group_layout, pipeline_layout)
+            })
+            .await,
+        ),
+    };
+    if explicit.is_some() {
+        extras.finished("create_layouts");
+    }
+
+    let compilation = &scenario.compilation;
+    let constants: HashMap<String, f64> = compilation.constants.iter().cloned().collect();
+    let render_pipeline = step(device, &mut errors, "create_render_pipeline", || {
+        timed(&mut timings, "create_render_pipeline", || {
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: None,
+                layout: explicit
+                    .as_ref()
+                    .map(|(_, pipeline_layout)| pipeline_layout),
+                vertex: VertexState {
+                    module: &shader01,
+                    entry_point: "main",
+                    compilation_options: PipelineCompilationOptions {
+                        constants: &HashMap::new(),
+                        zero_initialize_workgroup_memory: compilation
+                            .zero_initialize_workgroup_memory,
+                        vertex_pulling_transform: compilation.vertex_pulling_transform,
+                    },
+                    buffers: &[],
+                },
+                primitive: PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    unclipped_depth: false,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    conservative: false,
+                },
+                depth_stencil: None,
+                multisample: MultisampleState {
+                    count: 1,
+                    mask: 4294967295,
+                    alpha_to_coverage_enabled: false,
+                },
+                fragment: Some(FragmentState {
+                    module: &shader11,
+                    entry_point: fs_entry_point,
+                    compilation_options: PipelineCompilationOptions {
+                        constants: &constants,
+                        zero_initialize_workgroup_memory: compilation
+                            .zero_initialize_workgroup_memory,
+                        vertex_pulling_transform: compilation.vertex_pulling_transform,
+                    },
+                    targets: &[Some(ColorTargetState {
+                        format: scenario.view_format,
+                        blend: scenario.blend,
+                        write_mask: scenario.write_mask,
+                    })],
+                }),
+                multiview: None,
+                cache: extras.cache,
+            })
+        })
+    })
+    .await;
+    extras.finished("create_render_pipeline");
+
+    let bind_group = step(device, &mut errors, "create_bind_group", || {
+        let auto_layout;
+        let layout = match &explicit {
+            Some((bind_group_layout, _)) => bind_group_layout,
+            None => {
+                auto_layout = render_pipeline.get_bind_group_layout(0);
+                &auto_layout
+            }
+        };
+        let mut entries = vec![BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::TextureView(&view11),
+        }];
+        if let Some((sampler, offset)) = &sampler {
+            entries.push(BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            });
+            entries.push(BindGroupEntry {
+                binding: 2,
+                resource: offset.as_entire_binding(),
+            });
+        }
+        device.create_bind_group(&BindGroupDescriptor {
+            label: None,
+            layout,
+            entries: &entries,
+        })
+    })
+    .await;
+    extras.finished("create_bind_group");
+
+    let readback = device.create_buffer(&BufferDescriptor {
+        label: None,
+        size: (ROW_PITCH * SIZE) as u64,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    step(device, &mut errors, "render", || {
+        let cmd_buf = timed(&mut timings, "encode", || {
+            let mut cmd_enc =
+                device.create_command_encoder(&CommandEncoderDescriptor { label: None });
+            {
+                let mut render_pass = cmd_enc.begin_render_pass(&RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[Some(RenderPassColorAttachment {
+                        view: &view01,
+                        resolve_target: None,
+                        ops: Operations {
+                            load: scenario.load,
+                            store: scenario.store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: extras.timestamps.map(|query_set| {
+                        RenderPassTimestampWrites {
+                            query_set,
+                            beginning_of_pass_write_index: Some(0),
+                            end_of_pass_write_index: Some(1),
+                        }
+                    }),
+                    occlusion_query_set: extras.occlusion,
+                });
+                // Queries bracket the draw even when it is skipped, which must count nothing.
+                if extras.occlusion.is_some() {
+                    render_pass.begin_occlusion_query(0);
+                }
+                if let Some(query_set) = extras.statistics {
+                    render_pass.begin_pipeline_statistics_query(query_set, 0);
+                }
+                if scenario.draw {
+                    if let Some(r) = scenario.viewport {
+                        render_pass.set_viewport(
+                            r.x as f32,
+                            r.y as f32,
+                            r.width as f32,
+                            r.height as f32,
+                            0.0,
+                            1.0,
+                        );
+                    }
+                    if let Some(r) = scenario.scissor {
+                        render_pass.set_scissor_rect(r.x, r.y, r.width, r.height);
+                    }
+                    render_pass.set_pipeline(&render_pipeline);
+                    render_pass.set_bind_group(0, &bind_group, &[]);
+                    render_pass.draw(0..6, 0..1);
+                }
+                if extras.statistics.is_some() {
+                    render_pass.end_pipeline_statistics_query();
+                }
+                if extras.occlusion.is_some() {
+                    render_pass.end_occlusion_query();
+                }
+                drop(render_pass);
+            }
+            cmd_enc.copy_texture_to_buffer(
+                tex01.as_image_copy(),
+                ImageCopyBuffer {
+                    buffer: &readback,
+                    layout: wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(ROW_PITCH),
+                        rows_per_image: Some(SIZE),
+                    },
+                },
+                size,
+            );
+            cmd_enc.finish()
+        });
+        if scenario.destroy == Some(Destroy::BeforeSubmit) {
+            device.destroy();
+        }
+        timed(&mut timings, "submit", || queue.submit([cmd_buf]));
+    })
+    .await;
+    extras.finished("render");
+    // An invalid command buffer leaves `readback` zeroed, which would look like output.
+    let render_ok = !errors.iter().any(|err| err.step == "render");
+
+    let pixels = if render_ok {
+        let mapping = || {
+            if scenario.destroy == Some(Destroy::DuringMap) {
+                device.destroy();
+            }
+        };
+        let start = Instant::now();
+        let mapped = if extras.nonblocking {
+            nonblocking::map_read(&readback, mapping).await
+        } else {
+            map_read(device, &readback, mapping)
+        };
+        timings.push(("poll", start.elapsed()));
+        match mapped {
+            Ok(data) => Some(strip_rows(&data)),
+            Err(message) => {
+                errors.push(StepError {
+                    step: "readback",
+                    message,
+                    logs: Vec::new(),
+                });
+                None
+            }
+        }
+    } else {
+        None
+    };
+    Outcome {
+        errors,
+        pixels,
+        timings,
+    }
+}
+
+/// The message a caught panic was raised with.
+pub fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<String>()
+        .map(String::as_str)
+        .or_else(|| payload.downcast_ref::<&str>().copied())
+        .unwrap_or("non-string payload")
+}
+
+/// Runs `f`, recording how much CPU time it took under `name`.
+fn timed<T>(
+    timings: &mut Vec<(&'static str, Duration)>,
+    name: &'static str,
+    f: impl FnOnce() -> T,
+) -> T {
+    let start = Instant::now();
+    let out = f();
+    timings.push((name, start.elapsed()));
+    out
+}
+
+/// `view_formats` for a texture of `format` that will be viewed as `view_format`.
+pub fn view_formats(
+    format: TextureFormat,
+    view_format: Option<&TextureFormat>,
+) -> &[TextureFormat] {
+    match view_format {
+        Some(view_format) if *view_format != format => std::slice::from_ref(view_format),
+        _ => &[],
+    }
+}
+
+/// Copies `ROW_PITCH`-strided `data`, such as `DATA`, into `texture` and
+/// waits for the copy to finish.
+pub fn upload(gpu: &Gpu, texture: &Texture, data: &[u8]) {
+    let index = submit_upload(gpu, texture, data);
+    gpu.device
+        .poll(wgpu::MaintainBase::WaitForSubmissionIndex(index));
+}
+
+/// Submits the copy [`upload`] makes without waiting for it.
+pub fn submit_upload(gpu: &Gpu, texture: &Texture, data: &[u8]) -> SubmissionIndex {
+    let Gpu { device, queue, .. } = gpu;
+    let mut cmd_encoder = device.create_command_encoder(&CommandEncoderDescriptor { label: None });
+    let buffer = device.create_buffer(&BufferDescriptor {
+        label: None,
+        size: data.len() as u64,
+        usage: BufferUsages::COPY_SRC,
+        mapped_at_creation: true,
+    });
+    {
+        let slice = buffer.slice(..);
+        slice.get_mapped_range_mut().copy_from_slice(data);
+    };
+    buffer.unmap();
+    cmd_encoder.copy_buffer_to_texture(
+        ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(ROW_PITCH),
+                rows_per_image: Some(SIZE),
+            },
+        },
+        texture.as_image_copy(),
+        Extent3d {
+            width: SIZE,
+            height: SIZE,
+            depth_or_array_layers: 1,
+        },
+    );
+    let cmd_buf = cmd_encoder.finish();
+    // Dropping `buffer` is fine with the copy still in flight.
+    queue.submit([cmd_buf])
+}
+
+/// Spreads tightly packed `SIZE`x`SIZE` texels over `ROW_PITCH` rows, the
+/// layout [`upload`] expects.
+pub fn pad_rows(texels: &[u8]) -> Vec<u8> {
+    let mut data = vec![0; (ROW_PITCH * SIZE) as usize];
+    for (row, texels) in data
+        .chunks_mut(ROW_PITCH as usize)
+        .zip(texels.chunks((SIZE * 4) as usize))
+    {
+        row[..texels.len()].copy_from_slice(texels);
+    }
+    data
+}
+
+/// Maps a buffer and returns a copy of its contents.
+pub fn read_buffer(device: &Device, buffer: &wgpu::Buffer) -> Option<Vec<u8>> {
+    map_read(device, buffer, || {}).ok()
+}
+
+/// [`read_buffer`], calling `mapping` while the map is pending and
+/// reporting why the map failed.
+pub fn map_read(
+    device: &Device,
+    buffer: &wgpu::Buffer,
+    mapping: impl FnOnce(),
+) -> Result<Vec<u8>, String> {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let slice = buffer.slice(..);
+    slice.map_async(MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    mapping();
+    device.poll(wgpu::Maintain::Wait);
+    match receiver.try_recv() {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => return Err(format!("map_async failed: {err}")),
+        Err(_) => return Err("map_async callback never fired".to_string()),
+    }
+    let contents = slice.get_mapped_range().to_vec();
+    buffer.unmap();
+    Ok(contents)
+}
+
+/// Resolves `count` 64-bit values from the start of `query_set`.
+pub fn resolve_queries(gpu: &Gpu, query_set: &QuerySet, count: u32) -> Option<Vec<u64>> {
+    let Gpu { device, queue, .. } = gpu;
+    let size = count as u64 * 8;
+    let resolve = device.create_buffer(&BufferDescriptor {
+        label: None,
+        size,
+        usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    let readback = device.create_buffer(&BufferDescriptor {
+        label: None,
+        size,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut cmd_enc = device.create_command_encoder(&CommandEncoderDescriptor { label: None });
+    cmd_enc.resolve_query_set(query_set, 0..count, &resolve, 0);
+    cmd_enc.copy_buffer_to_buffer(&resolve, 0, &readback, 0, size);
+    queue.submit([cmd_enc.finish()]);
+    let values = read_buffer(device, &readback)?
+        .chunks(8)
+        .map(|v| u64::from_le_bytes(v.try_into().unwrap()))
+        .collect();
+    Some(values)
+}
+
+/// Maps a `ROW_PITCH`-strided readback buffer and strips the row padding.
+pub fn read_texels(device: &Device, buffer: &wgpu::Buffer) -> Option<Vec<u8>> {
+    read_buffer(device, buffer).map(|data| strip_rows(&data))
+}
+
+fn strip_rows(data: &[u8]) -> Vec<u8> {
+    data.chunks(ROW_PITCH as usize)
+        .flat_map(|row| &row[..(SIZE * 4) as usize])
+        .copied()
+        .collect()
+}
@@ -0,0 +1,333 @@
+//! Local results history: every run appends its [`Record`]s to an
+//! append-only JSON lines file (`--history=PATH`, default [`PATH`];
+//! `--no-history` skips it), which these commands query:
+//!
+//! - `history [case]`: every recorded outcome of cases whose name contains
+//!   `case`, oldest first.
+//! - `regressions <old-rev> <new-rev>`: cases that passed on `old-rev` and
+//!   fail on `new-rev`, and the other way round. Revisions match by prefix,
+//!   so `regressions c9a2d972 0a76c0fa` works.
+//! - `flaky`: cases that both passed and failed on the same revision.
+//!
+//! Outcomes are keyed by mode, case, parameters, adapter and instance
+//! settings, so the same case on two adapters, or with validation on and
+//! off, is tracked separately.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+
+use crate::cli::Args;
+use crate::results::{Record, Status};
+
+/// Outside `target/`, so `cargo clean` keeps it.
+pub const PATH: &str = "results/history.jsonl";
+
+/// What identifies a case across runs.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Key {
+    mode: String,
+    case: String,
+    adapter: String,
+    parameters: BTreeMap<String, String>,
+    instance: BTreeMap<String, String>,
+}
+
+impl Key {
+    fn of(record: &Record) -> Self {
+        Key {
+            mode: record.mode.clone(),
+            case: record.case.clone(),
+            adapter: record
+                .adapter
+                .as_ref()
+                .map_or_else(|| "no adapter".to_string(), |a| a.label()),
+            parameters: record.parameters.clone(),
+            instance: record.instance.clone(),
+        }
+    }
+
+    fn print(&self) {
+        println!("== {}: {} on {}", self.mode, self.case, self.adapter);
+    }
+}
+
+/// Passes and failures of one case on one revision.
+#[derive(Clone, Copy, Debug, Default)]
+struct Tally {
+    passed: usize,
+    failed: usize,
+}
+
+fn load(args: &Args) -> Option<Vec<Record>> {
+    let path = args.value("history").unwrap_or(PATH);
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            println!("no history at {path} yet");
+            return Some(Vec::new());
+        }
+        Err(err) => {
+            println!("failed to read {path}: {err}");
+            return None;
+        }
+    };
+    let mut records = Vec::new();
+    let mut unreadable = 0;
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        match serde_json::from_str::<Record>(line) {
+            Ok(record) => records.push(record),
+            Err(_) => unreadable += 1,
+        }
+    }
+    if unreadable != 0 {
+        println!("skipped {unreadable} unreadable lines in {path}");
+    }
+    records.sort_by_key(|record| record.time);
+    Some(records)
+}
+
+/// Tallies of every case on every revision it ran on.
+fn tallies(records: &[Record]) -> BTreeMap<Key, BTreeMap<String, Tally>> {
+    let mut tallies: BTreeMap<Key, BTreeMap<String, Tally>> = BTreeMap::new();
+    for record in records {
+        let tally = tallies
+            .entry(Key::of(record))
+            .or_default()
+            .entry(record.wgpu_revision.clone())
+            .or_default();
+        match record.status {
+            Status::Passed => tally.passed += 1,
+            Status::Failed => tally.failed += 1,
+            Status::Skipped => {}
+        }
+    }
+    tallies
+}
+
+/// Returns `false` if the history couldn't be read.
+pub fn show(args: &Args) -> bool {
+    let Some(records) = load(args) else {
+        return false;
+    };
+    let filter = args.positional(0).unwrap_or("");
+    let mut cases: BTreeMap<Key, Vec<&Record>> = BTreeMap::new();
+    for record in records.iter().filter(|r| r.case.contains(filter)) {
+        cases.entry(Key::of(record)).or_default().push(record);
+    }
+    for (key, records) in cases {
+        key.print();
+        for record in records {
+            let reason = record.reasons().into_iter().next().unwrap_or_default();
+            println!(
+                "{}  {:<10} {:<8} {}",
+                utc(record.time),
+                short(&record.wgpu_revision),
+                format!("{:?}", record.status).to_lowercase(),
+                reason.lines().next().unwrap_or_default(),
+            );
+        }
+    }
+    true
+}
+
+/// Returns `false` if the history couldn't be read or a case regressed.
+pub fn regressions(args: &Args) -> bool {
+    let (Some(old), Some(new)) = (args.positional(0), args.positional(1)) else {
+        eprintln!("usage: regressions <old-rev> <new-rev>");
+        std::process::exit(2);
+    };
+    let Some(records) = load(args) else {
+        return false;
+    };
+    let Changes { regressed, fixed } = changes(&records, old, new);
+    println!("regressed from {old} to {new}: {}", regressed.len());
+    for (key, after) in &regressed {
+        key.print();
+        println!(
+            "failed {} of {} runs on {new}",
+            after.failed,
+            after.passed + after.failed
+        );
+    }
+    println!("fixed from {old} to {new}: {}", fixed.len());
+    for (key, before) in &fixed {
+        key.print();
+        println!(
+            "failed {} of {} runs on {old}",
+            before.failed,
+            before.passed + before.failed
+        );
+    }
+    regressed.is_empty()
+}
+
+/// Cases that ran on both of two revisions and changed between them.
+#[derive(Debug, Default)]
+struct Changes {
+    /// Passed on the old revision, failed on the new one; with the new tally.
+    regressed: Vec<(Key, Tally)>,
+    /// Failed on the old revision, passed on the new one; with the old tally.
+    fixed: Vec<(Key, Tally)>,
+}
+
+fn changes(records: &[Record], old: &str, new: &str) -> Changes {
+    let on = |revisions: &BTreeMap<String, Tally>, prefix: &str| {
+        revisions
+            .iter()
+            .filter(|(revision, _)| revision.starts_with(prefix))
+            .fold(Tally::default(), |all, (_, tally)| Tally {
+                passed: all.passed + tally.passed,
+                failed: all.failed + tally.failed,
+            })
+    };
+    let mut changes = Changes::default();
+    for (key, revisions) in tallies(records) {
+        let (before, after) = (on(&revisions, old), on(&revisions, new));
+        if before.passed + before.failed == 0 || after.passed + after.failed == 0 {
+            continue;
+        }
+        if before.failed == 0 && after.failed != 0 {
+            changes.regressed.push((key, after));
+        } else if before.failed != 0 && after.failed == 0 {
+            changes.fixed.push((key, before));
+        }
+    }
+    changes
+}
+
+/// Returns `false` if the history couldn't be read or a case is flaky.
+pub fn flaky(args: &Args) -> bool {
+    let Some(records) = load(args) else {
+        return false;
+    };
+    let mut found = false;
+    for (key, revisions) in tallies(&records) {
+        for (revision, tally) in revisions {
+            if tally.passed != 0 && tally.failed != 0 {
+                key.print();
+                println!(
+                    "{}: passed {}, failed {}",
+                    short(&revision),
+                    tally.passed,
+                    tally.failed
+                );
+                found = true;
+            }
+        }
+    }
+    if !found {
+        println!("nothing flaky");
+    }
+    !found
+}
+
+/// Git revisions shortened the usual way; versions are left alone.
+fn short(revision: &str) -> &str {
+    if revision.len() == 40 && revision.bytes().all(|b| b.is_ascii_hexdigit()) {
+        &revision[..8]
+    } else {
+        revision
+    }
+}
+
+/// `YYYY-MM-DD hh:mm:ss` in UTC, from seconds since the Unix epoch.
+fn utc(time: u64) -> String {
+    let (days, seconds) = (time / 86400, time % 86400);
+    // Howard Hinnant's days-to-civil, shifted so years start in March.
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "c9a2d9720000000000000000000000000000000a";
+    const NEW: &str = "0a76c0fa0000000000000000000000000000000b";
+
+    fn record(case: &str, revision: &str, status: Status) -> Record {
+        Record {
+            mode: "run".to_string(),
+            wgpu_revision: revision.to_string(),
+            status,
+            ..Record::bare(case.to_string())
+        }
+    }
+
+    #[test]
+    fn utc_dates() {
+        assert_eq!(utc(0), "1970-01-01 00:00:00");
+        assert_eq!(utc(951_782_400), "2000-02-29 00:00:00");
+        assert_eq!(utc(1_700_000_000), "2023-11-14 22:13:20");
+        assert_eq!(utc(4_107_542_399), "2100-02-28 23:59:59");
+    }
+
+    #[test]
+    fn tallies_per_case_and_revision() {
+        let records = [
+            record("a", OLD, Status::Passed),
+            record("a", OLD, Status::Failed),
+            record("a", NEW, Status::Passed),
+            record("a", NEW, Status::Skipped),
+            record("b", OLD, Status::Passed),
+        ];
+        let tallies = tallies(&records);
+        assert_eq!(tallies.len(), 2);
+        let a = &tallies[&Key::of(&records[0])];
+        assert_eq!((a[OLD].passed, a[OLD].failed), (1, 1));
+        assert_eq!((a[NEW].passed, a[NEW].failed), (1, 0));
+        let b = &tallies[&Key::of(&records[4])];
+        assert_eq!(b.keys().collect::<Vec<_>>(), [OLD]);
+    }
+
+    #[test]
+    fn regressions_and_fixes() {
+        let records = [
+            record("regressed", OLD, Status::Passed),
+            record("regressed", NEW, Status::Passed),
+            record("regressed", NEW, Status::Failed),
+            record("fixed", OLD, Status::Failed),
+            record("fixed", NEW, Status::Passed),
+            record("steady", OLD, Status::Failed),
+            record("steady", NEW, Status::Failed),
+            record("new only", NEW, Status::Failed),
+        ];
+        let changes = changes(&records, "c9a2d972", "0a76c0fa");
+        let cases = |found: &[(Key, Tally)]| -> Vec<String> {
+            found.iter().map(|(key, _)| key.case.clone()).collect()
+        };
+        assert_eq!(cases(&changes.regressed), ["regressed"]);
+        assert_eq!(changes.regressed[0].1.failed, 1);
+        assert_eq!(changes.regressed[0].1.passed, 1);
+        assert_eq!(cases(&changes.fixed), ["fixed"]);
+
+        // Switching the revisions around swaps regressions and fixes.
+        let changes = super::changes(&records, "0a76c0fa", "c9a2d972");
+        assert_eq!(cases(&changes.regressed), ["fixed"]);
+        assert_eq!(cases(&changes.fixed), ["regressed"]);
+    }
+
+    #[test]
+    fn keys_tell_adapters_and_parameters_apart() {
+        let plain = record("a", OLD, Status::Passed);
+        let mut other = plain.clone();
+        other
+            .parameters
+            .insert("format".to_string(), "Bgra8Unorm".to_string());
+        assert_ne!(Key::of(&plain), Key::of(&other));
+    }
+}
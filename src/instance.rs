@@ -0,0 +1,221 @@
+//! How every mode creates its `Instance`:
+//!
+//! - `--instance-flags=FLAGS`, a comma-separated list of `debug`,
+//!   `validation`, `discard-hal-labels`, `gpu-based-validation` and
+//!   `allow-noncompliant`, or `none`. Without it, wgpu's defaults for this
+//!   build, adjusted by `WGPU_DEBUG`, `WGPU_VALIDATION` and friends.
+//! - `--dx12-compiler=fxc|dxc`, with `dxc` loading `dxcompiler.dll` and
+//!   `dxil.dll` the platform's usual way.
+//! - `--gles-minor-version=automatic|0|1|2`.
+//!
+//! The options set the defaults of [`Scenario::instance`], which a
+//! `--scenario` file can override under the same names. Running a failing scenario with backend
+//! validation on and off is often what tells a wgpu bug from a driver bug,
+//! so the settings go into every record and into `report` bundles.
+//!
+//! Only DX12 and Vulkan implement `gpu-based-validation`. Elsewhere the
+//! flag is ignored with a warning, and records say it wasn't applied.
+//!
+//! [`Scenario::instance`]: crate::scenario::Scenario::instance
+
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use wgpu::{Backend, Dx12Compiler, Gles3MinorVersion, Instance, InstanceDescriptor, InstanceFlags};
+
+use crate::cli::Args;
+
+/// `(option name, Rust name, flag)`.
+const FLAGS: [(&str, &str, InstanceFlags); 5] = [
+    ("debug", "DEBUG", InstanceFlags::DEBUG),
+    ("validation", "VALIDATION", InstanceFlags::VALIDATION),
+    (
+        "discard-hal-labels",
+        "DISCARD_HAL_LABELS",
+        InstanceFlags::DISCARD_HAL_LABELS,
+    ),
+    (
+        "gpu-based-validation",
+        "GPU_BASED_VALIDATION",
+        InstanceFlags::GPU_BASED_VALIDATION,
+    ),
+    (
+        "allow-noncompliant",
+        "ALLOW_UNDERLYING_NONCOMPLIANT_ADAPTER",
+        InstanceFlags::ALLOW_UNDERLYING_NONCOMPLIANT_ADAPTER,
+    ),
+];
+
+/// The parts of an `InstanceDescriptor` a scenario can set.
+#[derive(Clone, Debug)]
+pub struct Settings {
+    pub flags: InstanceFlags,
+    pub dx12_shader_compiler: Dx12Compiler,
+    pub gles_minor_version: Gles3MinorVersion,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            flags: InstanceFlags::default().with_env(),
+            dx12_shader_compiler: Dx12Compiler::default(),
+            gles_minor_version: Gles3MinorVersion::default(),
+        }
+    }
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+pub fn parse_flags(s: &str) -> Option<InstanceFlags> {
+    if s == "none" {
+        return Some(InstanceFlags::empty());
+    }
+    s.split(',')
+        .try_fold(InstanceFlags::empty(), |flags, name| {
+            let (_, _, flag) = FLAGS.iter().find(|(n, _, _)| *n == name.trim())?;
+            Some(flags | *flag)
+        })
+}
+
+pub fn parse_dx12_compiler(s: &str) -> Option<Dx12Compiler> {
+    Some(match s {
+        "fxc" => Dx12Compiler::Fxc,
+        "dxc" => Dx12Compiler::Dxc {
+            dxil_path: None,
+            dxc_path: None,
+        },
+        _ => return None,
+    })
+}
+
+pub fn parse_gles_minor_version(s: &str) -> Option<Gles3MinorVersion> {
+    Some(match s {
+        "automatic" => Gles3MinorVersion::Automatic,
+        "0" => Gles3MinorVersion::Version0,
+        "1" => Gles3MinorVersion::Version1,
+        "2" => Gles3MinorVersion::Version2,
+        _ => return None,
+    })
+}
+
+/// Reads the defaults from `args`. Exits with a usage error on a value it
+/// doesn't recognize.
+pub fn init(args: &Args) {
+    let defaults = Settings::default();
+    let settings = Settings {
+        flags: args
+            .parse("instance-flags", parse_flags)
+            .unwrap_or(defaults.flags),
+        dx12_shader_compiler: args
+            .parse("dx12-compiler", parse_dx12_compiler)
+            .unwrap_or(defaults.dx12_shader_compiler),
+        gles_minor_version: args
+            .parse("gles-minor-version", parse_gles_minor_version)
+            .unwrap_or(defaults.gles_minor_version),
+    };
+    let _ = SETTINGS.set(settings);
+}
+
+/// The settings from the command line, wgpu's defaults for what it
+/// leaves out.
+pub fn configured() -> Settings {
+    SETTINGS.get_or_init(Settings::default).clone()
+}
+
+/// A new instance with `settings`.
+pub fn new(settings: &Settings) -> Instance {
+    Instance::new(settings.descriptor())
+}
+
+/// Whether `backend` implements `InstanceFlags::GPU_BASED_VALIDATION`.
+pub fn supports_gpu_based_validation(backend: Backend) -> bool {
+    matches!(backend, Backend::Dx12 | Backend::Vulkan)
+}
+
+impl Settings {
+    pub fn descriptor(&self) -> InstanceDescriptor {
+        InstanceDescriptor {
+            flags: self.flags,
+            dx12_shader_compiler: self.dx12_shader_compiler.clone(),
+            gles_minor_version: self.gles_minor_version,
+            ..Default::default()
+        }
+    }
+
+    /// The settings by their option names, as the results record them.
+    pub fn summary(&self) -> BTreeMap<String, String> {
+        let flags: Vec<_> = FLAGS
+            .iter()
+            .filter(|(_, _, flag)| self.flags.contains(*flag))
+            .map(|(name, _, _)| *name)
+            .collect();
+        let dx12_shader_compiler = match self.dx12_shader_compiler {
+            Dx12Compiler::Fxc => "fxc",
+            Dx12Compiler::Dxc { .. } => "dxc",
+        };
+        let gles_minor_version = match self.gles_minor_version {
+            Gles3MinorVersion::Automatic => "automatic",
+            Gles3MinorVersion::Version0 => "0",
+            Gles3MinorVersion::Version1 => "1",
+            Gles3MinorVersion::Version2 => "2",
+        };
+        [
+            (
+                "instance-flags",
+                if flags.is_empty() {
+                    "none".to_string()
+                } else {
+                    flags.join(",")
+                },
+            ),
+            ("dx12-compiler", dx12_shader_compiler.to_string()),
+            ("gles-minor-version", gles_minor_version.to_string()),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect()
+    }
+
+    /// [`Settings::summary`] for a device on `backend`, which also says
+    /// whether a requested `gpu-based-validation` took effect.
+    pub fn summary_on(&self, backend: Backend) -> BTreeMap<String, String> {
+        let mut summary = self.summary();
+        if self.flags.contains(InstanceFlags::GPU_BASED_VALIDATION) {
+            let applied = if supports_gpu_based_validation(backend) {
+                "applied".to_string()
+            } else {
+                format!("unsupported on {backend:?}")
+            };
+            summary.insert("gpu-based-validation".to_string(), applied);
+        }
+        summary
+    }
+
+    /// The settings as a Rust `InstanceDescriptor` expression, for repros.
+    pub fn descriptor_expr(&self) -> String {
+        let flags: Vec<_> = FLAGS
+            .iter()
+            .filter(|(_, _, flag)| self.flags.contains(*flag))
+            .map(|(_, name, _)| format!("InstanceFlags::{name}"))
+            .collect();
+        let flags = if flags.is_empty() {
+            "InstanceFlags::empty()".to_string()
+        } else {
+            flags.join(" | ")
+        };
+        let dx12_shader_compiler = match self.dx12_shader_compiler {
+            Dx12Compiler::Fxc => "Dx12Compiler::Fxc".to_string(),
+            Dx12Compiler::Dxc { .. } => {
+                "Dx12Compiler::Dxc {\n            dxil_path: None,\n            dxc_path: None,\n        }"
+                    .to_string()
+            }
+        };
+        format!(
+            "InstanceDescriptor {{\n        flags: {flags},\n        \
+             dx12_shader_compiler: {dx12_shader_compiler},\n        \
+             gles_minor_version: Gles3MinorVersion::{:?},\n        \
+             ..Default::default()\n    }}",
+            self.gles_minor_version
+        )
+    }
+}
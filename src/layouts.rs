@@ -0,0 +1,68 @@
+//! `layouts`: run the scenario with an auto-derived and an explicit
+//! pipeline layout and report where they disagree.
+
+use crate::cli::Args;
+use crate::harness::{self, Gpu, Outcome};
+use crate::results::{self, Record};
+use crate::scenario::{self, Layout, Scenario, TextureBinding};
+
+/// Returns `false` if the two layouts led to different errors or pixels.
+pub async fn run(gpu: &Gpu, args: &Args, scenario: &Scenario) -> bool {
+    let defaults = TextureBinding::default();
+    let binding = TextureBinding {
+        sample_type: args
+            .parse("sample-type", scenario::parse_sample_type)
+            .unwrap_or(defaults.sample_type),
+        view_dimension: args
+            .parse("view-dimension", scenario::parse_view_dimension)
+            .unwrap_or(defaults.view_dimension),
+        multisampled: args.flag("multisampled"),
+    };
+    let auto = Scenario {
+        layout: Layout::Auto,
+        ..scenario.clone()
+    };
+    let explicit = Scenario {
+        layout: Layout::Explicit(binding),
+        ..scenario.clone()
+    };
+    // Judged on agreement alone: both layouts failing the same way passes.
+    let mut record = Record::new(gpu, format!("auto vs explicit {binding:?}"), &explicit);
+
+    println!("== auto layout");
+    let auto = harness::run(gpu, &auto).await;
+    auto.print();
+    println!("== explicit layout {binding:?}");
+    let explicit = harness::run(gpu, &explicit).await;
+    explicit.print();
+
+    compare(&auto, &explicit, &mut record);
+    results::push(record)
+}
+
+fn compare(auto: &Outcome, explicit: &Outcome, record: &mut Record) {
+    let steps = |o: &Outcome| o.errors.iter().map(|e| e.step).collect::<Vec<_>>();
+    if steps(auto) != steps(explicit) {
+        record.fail(format!(
+            "validation differs: auto failed in {:?}, explicit in {:?}",
+            steps(auto),
+            steps(explicit)
+        ));
+    }
+    match (&auto.pixels, &explicit.pixels) {
+        (Some(a), Some(e)) => {
+            let texels = a.chunks(4).zip(e.chunks(4)).filter(|(a, e)| a != e).count();
+            if texels != 0 {
+                record.fail(format!("pixels differ in {texels} texels"));
+            }
+        }
+        (None, None) => {}
+        _ => record.fail("only one layout produced a readback"),
+    }
+    for reason in &record.failures {
+        println!("{reason}");
+    }
+    if record.failures.is_empty() {
+        println!("auto and explicit layouts agree");
+    }
+}
@@ -0,0 +1,178 @@
+//! `leaks`: count live wgpu objects before device creation, after every
+//! step of a run, and once everything the run created has been dropped,
+//! flagging any kind of object that outlives it.
+//!
+//! Counts come from `Instance::generate_report`; built with the `counters`
+//! feature, wgpu-hal's own object counters are reported next to them.
+
+use std::sync::Mutex;
+
+use wgpu::{Backend, Instance, Maintain};
+
+use crate::harness::{self, Extras, Gpu};
+use crate::instance;
+use crate::scenario::Scenario;
+
+/// Live objects of each kind, in a fixed order.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Snapshot(Vec<(&'static str, usize)>);
+
+impl Snapshot {
+    fn take(instance: &Instance, backend: Backend, device: Option<&wgpu::Device>) -> Self {
+        let mut counts = Vec::new();
+        if let Some(report) = instance.generate_report() {
+            let hub = report.hub_report(backend);
+            counts.extend([
+                ("devices", hub.devices.num_kept_from_user),
+                ("buffers", hub.buffers.num_kept_from_user),
+                ("textures", hub.textures.num_kept_from_user),
+                ("texture views", hub.texture_views.num_kept_from_user),
+                ("samplers", hub.samplers.num_kept_from_user),
+                ("bind groups", hub.bind_groups.num_kept_from_user),
+                (
+                    "bind group layouts",
+                    hub.bind_group_layouts.num_kept_from_user,
+                ),
+                ("pipeline layouts", hub.pipeline_layouts.num_kept_from_user),
+                ("shader modules", hub.shader_modules.num_kept_from_user),
+                ("render pipelines", hub.render_pipelines.num_kept_from_user),
+                (
+                    "compute pipelines",
+                    hub.compute_pipelines.num_kept_from_user,
+                ),
+                ("query sets", hub.query_sets.num_kept_from_user),
+                ("command buffers", hub.command_buffers.num_kept_from_user),
+            ]);
+        }
+        if let Some(device) = device.filter(|_| cfg!(feature = "counters")) {
+            let hal = device.get_internal_counters().hal;
+            counts.extend(
+                [
+                    ("hal buffers", hal.buffers.read()),
+                    ("hal textures", hal.textures.read()),
+                    ("hal texture views", hal.texture_views.read()),
+                    ("hal samplers", hal.samplers.read()),
+                    ("hal bind groups", hal.bind_groups.read()),
+                    ("hal bind group layouts", hal.bind_group_layouts.read()),
+                    ("hal pipeline layouts", hal.pipeline_layouts.read()),
+                    ("hal shader modules", hal.shader_modules.read()),
+                    ("hal render pipelines", hal.render_pipelines.read()),
+                    ("hal query sets", hal.query_sets.read()),
+                ]
+                .map(|(name, count)| (name, count.max(0) as usize)),
+            );
+        }
+        Snapshot(counts)
+    }
+
+    fn get(&self, name: &str) -> usize {
+        self.0
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map_or(0, |&(_, count)| count)
+    }
+
+    /// Kinds whose count changed since `before`, with both counts.
+    fn changes(&self, before: &Snapshot) -> Vec<(&'static str, usize, usize)> {
+        self.0
+            .iter()
+            .map(|&(name, count)| (name, before.get(name), count))
+            .filter(|(_, before, after)| before != after)
+            .collect()
+    }
+
+    fn print(&self) {
+        let counts: Vec<_> = self
+            .0
+            .iter()
+            .filter(|(_, count)| *count != 0)
+            .map(|(name, count)| format!("{name}: {count}"))
+            .collect();
+        if counts.is_empty() {
+            println!("  nothing alive");
+        } else {
+            println!("  {}", counts.join(", "));
+        }
+    }
+}
+
+fn print_changes(changes: &[(&str, usize, usize)]) {
+    for (name, before, after) in changes {
+        println!("  {name}: {before} -> {after}");
+    }
+}
+
+/// Returns `false` if any object created by the run is still alive after it.
+pub async fn run(scenario: &Scenario) -> bool {
+    let instance = instance::new(&scenario.instance);
+    let adapter = harness::request_adapter(&instance).await;
+    let backend = adapter.get_info().backend;
+    if instance.generate_report().is_none() {
+        println!("no report from this instance");
+        return false;
+    }
+    if !cfg!(feature = "counters") {
+        println!("built without `counters`, wgpu-hal objects not counted");
+    }
+
+    println!("== before device creation");
+    let initial = Snapshot::take(&instance, backend, None);
+    initial.print();
+
+    let gpu = harness::open(instance, &scenario.instance, adapter).await;
+    let snapshot = || Snapshot::take(&gpu.instance, backend, Some(&gpu.device));
+    println!("== after device creation");
+    let baseline = snapshot();
+    baseline.print();
+
+    let previous = Mutex::new(baseline.clone());
+    let after_step = |step: &'static str| {
+        let current = snapshot();
+        println!("== after {step}");
+        print_changes(&current.changes(&previous.lock().unwrap()));
+        *previous.lock().unwrap() = current;
+    };
+    let outcome = harness::run_with(
+        &gpu,
+        scenario,
+        Extras {
+            after_step: Some(&after_step),
+            ..Default::default()
+        },
+    )
+    .await;
+    outcome.print();
+    drop(outcome);
+
+    // Resources are freed once the device knows the GPU is done with them.
+    gpu.device.poll(Maintain::Wait);
+    println!("== after dropping everything from the run");
+    let after = snapshot();
+    after.print();
+    let leaked = after.changes(&baseline);
+    if leaked.is_empty() {
+        println!("nothing leaked");
+    } else {
+        println!("still alive:");
+        print_changes(&leaked);
+    }
+    leaked.is_empty() && report_device_drop(gpu, backend, &initial)
+}
+
+/// Drops the device too, which should take the count back to where it was
+/// before it was created.
+fn report_device_drop(gpu: Gpu, backend: Backend, initial: &Snapshot) -> bool {
+    let Gpu {
+        instance,
+        device,
+        queue,
+        ..
+    } = gpu;
+    drop((queue, device));
+    println!("== after dropping the device");
+    let after = Snapshot::take(&instance, backend, None);
+    after.print();
+    let leaked = after.changes(initial);
+    print_changes(&leaked);
+    leaked.is_empty()
+}
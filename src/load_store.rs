@@ -0,0 +1,81 @@
+//! `load-store`: clear colors, `LoadOp::Load` over uploaded contents and
+//! `StoreOp::Discard` on `view01`, each against a computed expectation.
+
+use wgpu::{Color, LoadOp, StoreOp};
+
+use crate::harness::{self, Gpu};
+use crate::reference;
+use crate::results::{self, Record};
+use crate::scenario::Scenario;
+
+/// Linear clear colors, picked to straddle the sRGB curve's linear segment
+/// (below 0.0031308) and its mid-tones.
+const CLEAR_COLORS: [Color; 4] = [
+    Color {
+        r: 0.5,
+        g: 0.25,
+        b: 0.125,
+        a: 0.75,
+    },
+    Color {
+        r: 0.002,
+        g: 0.0031308,
+        b: 0.04,
+        a: 1.0,
+    },
+    Color {
+        r: 1.0,
+        g: 0.0,
+        b: 0.214,
+        a: 0.5,
+    },
+    Color {
+        r: 0.9,
+        g: 0.6,
+        b: 0.01,
+        a: 0.2,
+    },
+];
+
+/// Returns `false` if any case raised errors or disagreed with its expectation.
+pub async fn run(gpu: &Gpu, scenario: &Scenario) -> bool {
+    let mut cases = Vec::new();
+    for color in CLEAR_COLORS {
+        for view_format in [scenario.view_format, scenario.format] {
+            cases.push(Scenario {
+                view_format,
+                load: LoadOp::Clear(color),
+                draw: false,
+                ..scenario.clone()
+            });
+        }
+    }
+    for draw in [false, true] {
+        cases.push(Scenario {
+            load: LoadOp::Load,
+            draw,
+            ..scenario.clone()
+        });
+        cases.push(Scenario {
+            store: StoreOp::Discard,
+            draw,
+            ..scenario.clone()
+        });
+    }
+
+    let mut ok = true;
+    for case in cases {
+        let name = format!(
+            "{:?} -> {:?}, {:?}, draw: {}",
+            case.load, case.view_format, case.store, case.draw
+        );
+        println!("== {name}");
+        let outcome = harness::run(gpu, &case).await;
+        outcome.print();
+        let mut record = Record::new(gpu, name, &case);
+        record.add_outcome(&outcome);
+        record.compare(&outcome, &reference::render(&case), reference::TOLERANCE);
+        ok &= results::push(record);
+    }
+    ok
+}
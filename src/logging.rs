@@ -0,0 +1,162 @@
+//! wgpu's `log` output, filtered per crate by `--log=SPEC`, or `RUST_LOG`
+//! without it, e.g. `--log=warn,wgpu_core=info,wgpu_hal=debug,naga=off`.
+//! A bare level applies to every crate without a level of its own, and the
+//! longest matching crate or module prefix wins. Defaults to `warn`.
+//!
+//! Records that pass the filter go to stderr. Those emitted while a
+//! [`step`](crate::harness::step) runs are also kept, and a step that
+//! raises an error carries them into the results, so the backend messages
+//! leading up to it show next to it.
+
+use std::cell::RefCell;
+use std::sync::OnceLock;
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+use crate::cli::Args;
+
+const DEFAULT: &str = "warn";
+
+/// `(target prefix, level)`, with an empty prefix for the bare level.
+static FILTERS: OnceLock<Vec<(String, LevelFilter)>> = OnceLock::new();
+
+thread_local! {
+    /// Lines logged on this thread since [`capture`] started, if it did.
+    static CAPTURED: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+struct Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let filters = FILTERS.get().map_or(&[][..], Vec::as_slice);
+        metadata.level() <= level(filters, metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format!("{} {}: {}", record.level(), record.target(), record.args());
+        eprintln!("[{line}]");
+        CAPTURED.with_borrow_mut(|captured| {
+            if let Some(captured) = captured {
+                captured.push(line);
+            }
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+/// The level of the longest prefix in `filters` that `target` starts with.
+fn level(filters: &[(String, LevelFilter)], target: &str) -> LevelFilter {
+    filters
+        .iter()
+        .filter(|(prefix, _)| {
+            prefix.is_empty()
+                || target
+                    .strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+        })
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map_or(LevelFilter::Off, |&(_, level)| level)
+}
+
+fn parse(spec: &str) -> Option<Vec<(String, LevelFilter)>> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|directive| !directive.is_empty())
+        .map(|directive| match directive.split_once('=') {
+            Some((target, level)) => Some((target.to_string(), level.parse().ok()?)),
+            None => Some((String::new(), directive.parse().ok()?)),
+        })
+        .collect()
+}
+
+/// Installs the logger with the filters `args` asks for. Exits with a
+/// usage error on a malformed spec.
+pub fn init(args: &Args) {
+    let env = std::env::var("RUST_LOG").ok();
+    let spec = args.value("log").or(env.as_deref()).unwrap_or(DEFAULT);
+    let Some(filters) = parse(spec) else {
+        eprintln!("unrecognized log filter: {spec}, expected e.g. warn,wgpu_core=info");
+        std::process::exit(2);
+    };
+    let max = filters
+        .iter()
+        .map(|&(_, level)| level)
+        .max()
+        .unwrap_or(LevelFilter::Off);
+    let _ = FILTERS.set(filters);
+    if log::set_logger(&Logger).is_ok() {
+        log::set_max_level(max);
+    }
+}
+
+/// Runs `f`, returning what it logged on this thread along with its result.
+pub fn capture<T>(f: impl FnOnce() -> T) -> (T, Vec<String>) {
+    let outer = CAPTURED.replace(Some(Vec::new()));
+    let out = f();
+    let lines = CAPTURED.replace(outer).unwrap_or_default();
+    // Nested captures see their lines too.
+    CAPTURED.with_borrow_mut(|captured| {
+        if let Some(captured) = captured {
+            captured.extend(lines.iter().cloned());
+        }
+    });
+    (out, lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_levels_and_targets() {
+        assert_eq!(
+            parse("warn"),
+            Some(vec![(String::new(), LevelFilter::Warn)])
+        );
+        assert_eq!(
+            parse(" info , wgpu_core=debug,naga=off "),
+            Some(vec![
+                (String::new(), LevelFilter::Info),
+                ("wgpu_core".to_string(), LevelFilter::Debug),
+                ("naga".to_string(), LevelFilter::Off),
+            ])
+        );
+        assert_eq!(parse(""), Some(Vec::new()));
+    }
+
+    #[test]
+    fn parse_rejects_malformed_specs() {
+        assert_eq!(parse("loud"), None);
+        assert_eq!(parse("warn,wgpu_core=loud"), None);
+        assert_eq!(parse("wgpu_core=info=debug"), None);
+    }
+
+    #[test]
+    fn level_by_longest_prefix() {
+        let filters = parse("warn,wgpu=info,wgpu_core=debug,wgpu_core::device=trace").unwrap();
+        assert_eq!(level(&filters, "naga::front"), LevelFilter::Warn);
+        assert_eq!(level(&filters, "wgpu"), LevelFilter::Info);
+        assert_eq!(level(&filters, "wgpu_core"), LevelFilter::Debug);
+        assert_eq!(level(&filters, "wgpu_core::command"), LevelFilter::Debug);
+        assert_eq!(
+            level(&filters, "wgpu_core::device::global"),
+            LevelFilter::Trace
+        );
+        // A crate name is a prefix of another's, not a module of it.
+        assert_eq!(level(&filters, "wgpu_core_x"), LevelFilter::Warn);
+        assert_eq!(level(&filters, "wgpu_hal::gles"), LevelFilter::Warn);
+    }
+
+    #[test]
+    fn level_without_a_bare_level() {
+        let filters = parse("wgpu_core=info").unwrap();
+        assert_eq!(level(&filters, "wgpu_core"), LevelFilter::Info);
+        assert_eq!(level(&filters, "wgpu_hal"), LevelFilter::Off);
+        assert_eq!(level(&[], "wgpu_core"), LevelFilter::Off);
+    }
+}
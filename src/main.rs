@@ -1,237 +1,136 @@
-use std::borrow::Cow;
-use std::collections::HashMap;
+use std::path::Path;
 
-use wgpu::{
-    BindGroupDescriptor, BindGroupEntry, BufferDescriptor, BufferUsages, Color, ColorTargetState,
-    ColorWrites, CommandEncoderDescriptor, DeviceDescriptor, Extent3d, Features, FragmentState,
-    ImageCopyBuffer, Instance, InstanceDescriptor, Limits, MultisampleState, Operations,
-    PipelineCompilationOptions, PrimitiveState, RenderPassColorAttachment, RenderPassDescriptor,
-    RenderPipelineDescriptor, RequestAdapterOptions, ShaderModuleDescriptor, ShaderSource,
-    TextureAspect, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
-    TextureViewDescriptor, VertexState,
-};
+use cli::Args;
+use results::Record;
+use scenario::Scenario;
 
-fn main() {
-    println!("Hello, world!");
-    pollster::block_on(run());
-}
-
-async fn run() {
-    let i = Instance::new(InstanceDescriptor {
-        ..Default::default()
-    });
-    let adapter = i
-        .request_adapter(&RequestAdapterOptions {
-            power_preference: wgpu::PowerPreference::None,
-            force_fallback_adapter: false,
-            compatible_surface: None,
-        })
-        .await
-        .unwrap();
-    let (device, queue) = adapter
-        .request_device(
-            &DeviceDescriptor {
-                label: None,
-                required_features: Features::default(),
-                required_limits: Limits::default(),
-            },
-            None,
-        )
-        .await
-        .unwrap();
-    device.on_uncaptured_error(Box::new(|err| println!("ERROR: {err:?}")));
-    // format=rgba8unorm
-    // viewFormat=rgba8unorm-srgb
-    // sampleCount=1
-    let tex01 = device.create_texture(&TextureDescriptor {
-        label: None,
-        size: Extent3d {
-            width: 16,
-            height: 16,
-            depth_or_array_layers: 1,
-        },
-        mip_level_count: 1,
-        sample_count: 1,
-        dimension: TextureDimension::D2,
-        format: TextureFormat::Rgba8Unorm,
-        usage: TextureUsages::COPY_SRC | TextureUsages::RENDER_ATTACHMENT,
-        view_formats: &[TextureFormat::Rgba8UnormSrgb],
-    });
+mod async_runner;
+mod bench;
+mod blend;
+mod cli;
+mod compilation;
+mod compute;
+mod copy;
+mod device_loss;
+mod differential;
+mod expectations;
+mod fuzz;
+mod harness;
+mod history;
+mod instance;
+mod layouts;
+mod leaks;
+mod load_store;
+mod logging;
+mod nonblocking;
+mod pipeline_cache;
+mod queries;
+mod reference;
+mod regions;
+mod report;
+mod repro;
+mod results;
+mod sampling;
+mod scenario;
+mod shaders;
+mod stress;
+mod translate;
 
-    let tex11 = device.create_texture(&TextureDescriptor {
-        label: None,
-        size: Extent3d {
-            width: 16,
-            height: 16,
-            depth_or_array_layers: 1,
-        },
-        mip_level_count: 1,
-        sample_count: 1,
-        dimension: TextureDimension::D2,
-        format: TextureFormat::Rgba8Unorm,
-        usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
-        view_formats: &[],
-    });
-
-    let mut cmd_encoder = device.create_command_encoder(&CommandEncoderDescriptor { label: None });
-    let buffer = device.create_buffer(&BufferDescriptor {
-        label: None,
-        size: 4096,
-        usage: BufferUsages::COPY_SRC,
-        mapped_at_creation: true,
-    });
-    {
-        let slice = buffer.slice(..);
-        slice.get_mapped_range_mut().copy_from_slice(&DATA);
+fn main() {
+    let args = Args::from_env();
+    logging::init(&args);
+    instance::init(&args);
+    // Queries of the history, which aren't runs to record in it.
+    let query = match args.command.as_deref() {
+        Some("history") => Some(history::show(&args)),
+        Some("regressions") => Some(history::regressions(&args)),
+        Some("flaky") => Some(history::flaky(&args)),
+        _ => None,
     };
-    buffer.unmap();
-    cmd_encoder.copy_buffer_to_texture(
-        ImageCopyBuffer {
-            buffer: &buffer,
-            layout: wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(256),
-                rows_per_image: Some(16),
-            },
-        },
-        tex11.as_image_copy(),
-        Extent3d {
-            width: 16,
-            height: 16,
-            depth_or_array_layers: 1,
-        },
-    );
-    let cmd_buf = cmd_encoder.finish();
-    device.poll(wgpu::MaintainBase::WaitForSubmissionIndex(
-        queue.submit([cmd_buf]),
-    ));
-    buffer.destroy();
-    let view01 = tex01.create_view(&TextureViewDescriptor {
-        label: None,
-        format: Some(TextureFormat::Rgba8UnormSrgb),
-        dimension: None,
-        aspect: TextureAspect::All,
-        base_mip_level: 0,
-        mip_level_count: None,
-        base_array_layer: 0,
-        array_layer_count: None,
-    });
-    let shader01 = device.create_shader_module(ShaderModuleDescriptor {
-        label: None,
-        source: ShaderSource::Wgsl(Cow::Owned(
-            "
-@vertex fn main(@builtin(vertex_index) VertexIndex : u32) -> @builtin(position) vec4<f32> {
-    var pos = array<vec2<f32>, 6>(
-        vec2<f32>(-1.0, -1.0),
-        vec2<f32>(-1.0,  1.0),
-        vec2<f32>( 1.0, -1.0),
-        vec2<f32>(-1.0,  1.0),
-        vec2<f32>( 1.0, -1.0),
-        vec2<f32>( 1.0,  1.0)
-    );
-    return vec4<f32>(pos[VertexIndex], 0.0, 1.0);
-}"
-            .to_string(),
-        )),
-    });
-    let shader11 = device.create_shader_module(ShaderModuleDescriptor {
-        label: None,
-        source: ShaderSource::Wgsl(Cow::Owned(
-            "
-@group(0) @binding(0) var src: texture_2d<f32>;
-@fragment fn main(@builtin(position) coord: vec4<f32>) -> @location(0) vec4<f32> {
-    return textureLoad(src, vec2<i32>(coord.xy), 0);
-}"
-            .to_string(),
-        )),
-    });
-    let render_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
-        label: None,
-        layout: None,
-        vertex: VertexState {
-            module: &shader01,
-            entry_point: "main",
-            compilation_options: PipelineCompilationOptions {
-                constants: &HashMap::new(),
-                zero_initialize_workgroup_memory: true,
-                vertex_pulling_transform: false,
-            },
-            buffers: &[],
-        },
-        primitive: PrimitiveState {
-            topology: wgpu::PrimitiveTopology::TriangleList,
-            strip_index_format: None,
-            front_face: wgpu::FrontFace::Ccw,
-            cull_mode: None,
-            unclipped_depth: false,
-            polygon_mode: wgpu::PolygonMode::Fill,
-            conservative: false,
-        },
-        depth_stencil: None,
-        multisample: MultisampleState {
-            count: 1,
-            mask: 4294967295,
-            alpha_to_coverage_enabled: false,
-        },
-        fragment: Some(FragmentState {
-            module: &shader11,
-            entry_point: "main",
-            compilation_options: PipelineCompilationOptions {
-                constants: &HashMap::new(),
-                zero_initialize_workgroup_memory: true,
-                vertex_pulling_transform: false,
-            },
-            targets: &[Some(ColorTargetState {
-                format: TextureFormat::Rgba8UnormSrgb,
-                blend: None,
-                write_mask: ColorWrites::RED
-                    | ColorWrites::GREEN
-                    | ColorWrites::BLUE
-                    | ColorWrites::ALPHA,
-            })],
-        }),
-        multiview: None,
-        cache: None,
-    });
-    let view11 = tex11.create_view(&TextureViewDescriptor {
-        label: None,
-        format: None,
-        dimension: None,
-        aspect: TextureAspect::All,
-        base_mip_level: 0,
-        mip_level_count: None,
-        base_array_layer: 0,
-        array_layer_count: None,
-    });
-    let bind_group = device.create_bind_group(&BindGroupDescriptor {
-        label: None,
-        layout: &render_pipeline.get_bind_group_layout(0),
-        entries: &[BindGroupEntry {
-            binding: 0,
-            resource: wgpu::BindingResource::TextureView(&view11),
-        }],
-    });
-    let mut cmd_enc = device.create_command_encoder(&CommandEncoderDescriptor { label: None });
-    {
-        let mut render_pass = cmd_enc.begin_render_pass(&RenderPassDescriptor {
-            label: None,
-            color_attachments: &[Some(RenderPassColorAttachment {
-                view: &view01,
-                resolve_target: None,
-                ops: Operations {
-                    load: wgpu::LoadOp::Clear(Color::TRANSPARENT),
-                    store: wgpu::StoreOp::Store,
-                },
-            })],
-            depth_stencil_attachment: None,
-            timestamp_writes: None,
-            occlusion_query_set: None,
-        });
-        render_pass.set_pipeline(&render_pipeline);
-        render_pass.set_bind_group(0, &bind_group, &[]);
-        render_pass.draw(0..6, 0..1);
-        drop(render_pass);
+    if let Some(ok) = query {
+        std::process::exit(if ok { 0 } else { 1 });
     }
+    results::start(&args);
+    let scenario = scenario::load(&args);
+    let ok = match args.command.as_deref() {
+        Some("shaders") => {
+            let out_dir = args.positional(0).unwrap_or("target/shaders");
+            translate::run(Path::new(out_dir))
+        }
+        Some("shaders-diff") => {
+            let out_dir = args.positional(0).unwrap_or("target/shaders-diff");
+            translate::diff(Path::new(out_dir))
+        }
+        Some("layouts") => {
+            let gpu = pollster::block_on(harness::init(&scenario));
+            pollster::block_on(layouts::run(&gpu, &args, &scenario))
+        }
+        Some("compute") => {
+            let gpu = pollster::block_on(harness::init(&scenario));
+            pollster::block_on(compute::run(&gpu, &scenario))
+        }
+        Some("copy") => {
+            let gpu = pollster::block_on(harness::init(&scenario));
+            pollster::block_on(copy::run(&gpu, &scenario))
+        }
+        Some("sampling") => {
+            let gpu = pollster::block_on(harness::init(&scenario));
+            pollster::block_on(sampling::run(&gpu, &scenario))
+        }
+        Some("blend") => {
+            let gpu = pollster::block_on(harness::init(&scenario));
+            pollster::block_on(blend::run(&gpu, &scenario))
+        }
+        Some("load-store") => {
+            let gpu = pollster::block_on(harness::init(&scenario));
+            pollster::block_on(load_store::run(&gpu, &scenario))
+        }
+        Some("regions") => {
+            let gpu = pollster::block_on(harness::init(&scenario));
+            pollster::block_on(regions::run(&gpu, &scenario))
+        }
+        Some("compilation") => {
+            let gpu = pollster::block_on(harness::init(&scenario));
+            pollster::block_on(compilation::run(&gpu, &scenario))
+        }
+        Some("pipeline-cache") => {
+            let path = args.positional(0).unwrap_or("target/pipeline-cache.bin");
+            let gpu = pollster::block_on(harness::init(&scenario));
+            pollster::block_on(pipeline_cache::run(&gpu, &scenario, Path::new(path)))
+        }
+        Some("bench") => pollster::block_on(bench::run(&args, &scenario)),
+        Some("queries") => {
+            let gpu = pollster::block_on(harness::init(&scenario));
+            pollster::block_on(queries::run(&gpu, &scenario))
+        }
+        Some("leaks") => pollster::block_on(leaks::run(&scenario)),
+        Some("device-loss") => device_loss::run(&scenario),
+        Some("stress") => stress::run(&args, &scenario),
+        Some("async") => async_runner::run(&args, &scenario),
+        Some("differential") => pollster::block_on(differential::run(&args, &scenario)),
+        Some("fuzz") => fuzz::run(&args, &scenario),
+        Some("report") => pollster::block_on(report::run(&args, &scenario)),
+        _ => {
+            println!("Hello, world!");
+            pollster::block_on(run(&scenario))
+        }
+    };
+    if !results::finish(&args, ok) {
+        std::process::exit(1);
+    }
+}
+
+/// Returns `false` if the default scenario raised errors or disagreed with
+/// the reference.
+async fn run(scenario: &Scenario) -> bool {
+    let gpu = harness::init(scenario).await;
+    println!("{:?}", gpu.adapter.get_info());
+    let outcome = harness::run(&gpu, scenario).await;
+    outcome.print();
+    let mut record = Record::new(&gpu, "default", scenario);
+    record.add_outcome(&outcome);
+    record.compare(&outcome, &reference::render(scenario), reference::TOLERANCE);
+    results::push(record)
 }
 
 const DATA: [u8; 4096] = [
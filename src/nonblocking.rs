@@ -0,0 +1,110 @@
+//! Waiting on the GPU without blocking: futures completed by wgpu's own
+//! callbacks, with a background thread calling `poll(Maintain::Poll)` to
+//! make those callbacks fire. None of it depends on a particular executor.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use wgpu::{Buffer, Maintain, MapMode, Queue};
+
+use crate::harness::Gpu;
+
+struct Slot<T> {
+    value: Option<T>,
+    waker: Option<Waker>,
+}
+
+/// Resolves to whatever the paired callback is called with.
+struct Callback<T>(Arc<Mutex<Slot<T>>>);
+
+impl<T: Send + 'static> Callback<T> {
+    fn new() -> (Self, impl FnOnce(T) + Send + 'static) {
+        let slot = Arc::new(Mutex::new(Slot {
+            value: None,
+            waker: None,
+        }));
+        let sender = slot.clone();
+        let callback = move |value| {
+            let mut slot = sender.lock().unwrap();
+            slot.value = Some(value);
+            if let Some(waker) = slot.waker.take() {
+                waker.wake();
+            }
+        };
+        (Callback(slot), callback)
+    }
+}
+
+impl<T> Future for Callback<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut slot = self.0.lock().unwrap();
+        match slot.value.take() {
+            Some(value) => Poll::Ready(value),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Resolves once everything submitted to `queue` so far has finished.
+pub async fn work_done(queue: &Queue) {
+    let (done, callback) = Callback::new();
+    queue.on_submitted_work_done(move || callback(()));
+    done.await
+}
+
+/// `harness::map_read` driven by the `map_async` callback alone.
+pub async fn map_read(buffer: &Buffer, mapping: impl FnOnce()) -> Result<Vec<u8>, String> {
+    let (mapped, callback) = Callback::new();
+    let slice = buffer.slice(..);
+    slice.map_async(MapMode::Read, callback);
+    mapping();
+    mapped
+        .await
+        .map_err(|err| format!("map_async failed: {err}"))?;
+    let contents = slice.get_mapped_range().to_vec();
+    buffer.unmap();
+    Ok(contents)
+}
+
+/// Calls `poll(Maintain::Poll)` every `interval` on a background thread
+/// until dropped, the way an application's frame loop would.
+pub struct Poller {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Poller {
+    pub fn start(gpu: Arc<Gpu>, interval: Duration) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let thread = thread::spawn(move || {
+            while !stopped.load(Ordering::Relaxed) {
+                gpu.device.poll(Maintain::Poll);
+                thread::sleep(interval);
+            }
+        });
+        Poller {
+            stop,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for Poller {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
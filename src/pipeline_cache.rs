@@ -0,0 +1,180 @@
+//! `pipeline-cache [path]`: build the render pipeline through a
+//! `PipelineCache`, persist the cache to `path`, and check that pipelines
+//! built from reloaded or corrupted data behave like an uncached one.
+//!
+//! A file left by a previous run is reloaded before being overwritten, so
+//! running the mode twice covers the round trip through disk.
+
+use std::fs;
+use std::path::Path;
+
+use wgpu::{Features, PipelineCache, PipelineCacheDescriptor};
+
+use crate::harness::{self, step, Extras, Gpu, Outcome, StepError};
+use crate::reference;
+use crate::results::{self, Record};
+use crate::scenario::Scenario;
+
+/// Returns `false` if any cached pipeline raised errors or rendered
+/// differently from the uncached one.
+pub async fn run(gpu: &Gpu, scenario: &Scenario, path: &Path) -> bool {
+    if !gpu.device.features().contains(Features::PIPELINE_CACHE) {
+        println!("skipped, no PIPELINE_CACHE");
+        results::skip(
+            Record::new(gpu, "pipeline cache", scenario),
+            "no PIPELINE_CACHE",
+        );
+        return true;
+    }
+    println!(
+        "cache key: {:?}",
+        wgpu::util::pipeline_cache_key(&gpu.adapter.get_info())
+    );
+
+    println!("== uncached");
+    let uncached = harness::run(gpu, scenario).await;
+    uncached.print();
+    let mut record = Record::new(gpu, "uncached", scenario);
+    record.add_outcome(&uncached);
+    record.compare(
+        &uncached,
+        &reference::render(scenario),
+        reference::TOLERANCE,
+    );
+    let mut ok = results::push(record);
+
+    match fs::read(path) {
+        Ok(data) => {
+            println!("== reloaded from {}", path.display());
+            let mut record = Record::new(gpu, "reloaded", scenario);
+            record.artifacts.push(path.display().to_string());
+            check(gpu, scenario, &uncached, Some(&data), false, &mut record).await;
+            ok &= results::push(record);
+        }
+        Err(err) => println!("== nothing to reload from {}: {err}", path.display()),
+    }
+
+    println!("== fresh");
+    let mut record = Record::new(gpu, "fresh", scenario);
+    let (cache, fresh) = cached(gpu, scenario, None, false).await;
+    same(&uncached, &fresh, &mut record);
+    let Some(data) = cache.get_data() else {
+        println!("cache returned no data");
+        record.fail("cache returned no data");
+        results::push(record);
+        return false;
+    };
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    match fs::write(path, &data) {
+        Ok(()) => {
+            println!("wrote {} bytes to {}", data.len(), path.display());
+            record.artifacts.push(path.display().to_string());
+        }
+        Err(err) => {
+            println!("failed to write {}: {err}", path.display());
+            record.fail(format!("failed to write {}: {err}", path.display()));
+        }
+    }
+    ok &= results::push(record);
+
+    if data.is_empty() {
+        println!("skipped corruption cases, cache data is empty");
+        results::skip(
+            Record::new(gpu, "corrupted data", scenario),
+            "cache data is empty",
+        );
+        return ok;
+    }
+    // wgpu validates both before handing anything to the driver: the bad
+    // magic fails its header check, and the truncated copy no longer holds
+    // the payload size the header records.
+    let mut bad_magic = data.clone();
+    bad_magic[0] ^= 0xff;
+    let truncated = &data[..data.len() / 2];
+    for (name, data) in [("bad magic", &bad_magic[..]), ("truncated", truncated)] {
+        println!("== {name}, fallback");
+        let mut record = Record::new(gpu, format!("{name}, fallback"), scenario);
+        check(gpu, scenario, &uncached, Some(data), true, &mut record).await;
+        ok &= results::push(record);
+        println!("== {name}, no fallback");
+        let mut record = Record::new(gpu, format!("{name}, no fallback"), scenario);
+        let mut errors = Vec::new();
+        create(gpu, &mut errors, Some(data), false).await;
+        match errors.first() {
+            Some(err) => println!("rejected as expected: {}", err.message),
+            None => {
+                println!("accepted corrupted data");
+                record.fail("accepted corrupted data");
+            }
+        }
+        ok &= results::push(record);
+    }
+    ok
+}
+
+async fn create(
+    gpu: &Gpu,
+    errors: &mut Vec<StepError>,
+    data: Option<&[u8]>,
+    fallback: bool,
+) -> PipelineCache {
+    step(&gpu.device, errors, "create_pipeline_cache", || {
+        // SAFETY: `data` is either what `get_data` returned, possibly read
+        // back from disk, or that with wgpu's header damaged or its payload
+        // cut short, both of which wgpu rejects itself.
+        unsafe {
+            gpu.device.create_pipeline_cache(&PipelineCacheDescriptor {
+                label: None,
+                data,
+                fallback,
+            })
+        }
+    })
+    .await
+}
+
+/// Renders with a cache created from `data` and compares against `uncached`.
+async fn check(
+    gpu: &Gpu,
+    scenario: &Scenario,
+    uncached: &Outcome,
+    data: Option<&[u8]>,
+    fallback: bool,
+    record: &mut Record,
+) {
+    let (_, outcome) = cached(gpu, scenario, data, fallback).await;
+    same(uncached, &outcome, record);
+}
+
+/// Runs `scenario` through a cache created from `data`, counting errors
+/// from creating the cache as part of the outcome.
+async fn cached(
+    gpu: &Gpu,
+    scenario: &Scenario,
+    data: Option<&[u8]>,
+    fallback: bool,
+) -> (PipelineCache, Outcome) {
+    let mut errors = Vec::new();
+    let cache = create(gpu, &mut errors, data, fallback).await;
+    let extras = Extras {
+        cache: Some(&cache),
+        ..Default::default()
+    };
+    let mut outcome = harness::run_with(gpu, scenario, extras).await;
+    errors.append(&mut outcome.errors);
+    outcome.errors = errors;
+    outcome.print();
+    (cache, outcome)
+}
+
+fn same(uncached: &Outcome, cached: &Outcome, record: &mut Record) {
+    record.add_outcome(cached);
+    if cached.pixels.is_some() && cached.pixels == uncached.pixels {
+        println!("same pixels as uncached");
+    } else {
+        println!("pixels differ from uncached");
+        record.fail("pixels differ from uncached");
+    }
+}
@@ -0,0 +1,167 @@
+//! `queries`: wrap the draw in an occlusion query and, with
+//! `PIPELINE_STATISTICS_QUERY`, a pipeline-statistics query, as a signal
+//! independent of the pixels that the draw happened and covered what it
+//! should.
+
+use wgpu::{Features, PipelineStatisticsTypes, QuerySet, QuerySetDescriptor, QueryType};
+
+use crate::harness::{self, Extras, Gpu};
+use crate::reference;
+use crate::results::{self, Record};
+use crate::scenario::{Rect, Scenario};
+
+/// Statistics requested, in the order the query resolves them.
+const STATISTICS: [(&str, PipelineStatisticsTypes); 4] = [
+    (
+        "vertex invocations",
+        PipelineStatisticsTypes::VERTEX_SHADER_INVOCATIONS,
+    ),
+    (
+        "clipper invocations",
+        PipelineStatisticsTypes::CLIPPER_INVOCATIONS,
+    ),
+    (
+        "clipper primitives out",
+        PipelineStatisticsTypes::CLIPPER_PRIMITIVES_OUT,
+    ),
+    (
+        "fragment invocations",
+        PipelineStatisticsTypes::FRAGMENT_SHADER_INVOCATIONS,
+    ),
+];
+
+fn cases(scenario: &Scenario) -> Vec<(&'static str, Scenario)> {
+    let rect = |x, y, width, height| {
+        Some(Rect {
+            x,
+            y,
+            width,
+            height,
+        })
+    };
+    vec![
+        ("full", scenario.clone()),
+        (
+            "scissor 3,5 7x9",
+            Scenario {
+                scissor: rect(3, 5, 7, 9),
+                ..scenario.clone()
+            },
+        ),
+        (
+            "viewport 0,0 8x8",
+            Scenario {
+                viewport: rect(0, 0, 8, 8),
+                ..scenario.clone()
+            },
+        ),
+        (
+            "no draw",
+            Scenario {
+                draw: false,
+                ..scenario.clone()
+            },
+        ),
+    ]
+}
+
+/// Returns `false` if any case raised errors, disagreed with the reference,
+/// or counted something other than expected.
+pub async fn run(gpu: &Gpu, scenario: &Scenario) -> bool {
+    let occlusion = gpu.device.create_query_set(&QuerySetDescriptor {
+        label: None,
+        ty: QueryType::Occlusion,
+        count: 1,
+    });
+    let statistics = gpu
+        .device
+        .features()
+        .contains(Features::PIPELINE_STATISTICS_QUERY)
+        .then(|| {
+            gpu.device.create_query_set(&QuerySetDescriptor {
+                label: None,
+                ty: QueryType::PipelineStatistics(
+                    STATISTICS
+                        .iter()
+                        .fold(PipelineStatisticsTypes::empty(), |all, (_, ty)| all | *ty),
+                ),
+                count: 1,
+            })
+        });
+    if statistics.is_none() {
+        println!("no PIPELINE_STATISTICS_QUERY, only occlusion is checked");
+    }
+
+    let mut ok = true;
+    for (name, case) in cases(scenario) {
+        println!("== {name}");
+        let extras = Extras {
+            occlusion: Some(&occlusion),
+            statistics: statistics.as_ref(),
+            ..Default::default()
+        };
+        let outcome = harness::run_with(gpu, &case, extras).await;
+        outcome.print();
+        let mut record = Record::new(gpu, name, &case);
+        record.add_outcome(&outcome);
+        record.compare(&outcome, &reference::render(&case), reference::TOLERANCE);
+        if outcome.pixels.is_some() {
+            check_queries(gpu, &case, &occlusion, statistics.as_ref(), &mut record);
+        }
+        ok &= results::push(record);
+    }
+    ok
+}
+
+/// Checks what the queries around the draw of `case` counted.
+fn check_queries(
+    gpu: &Gpu,
+    case: &Scenario,
+    occlusion: &QuerySet,
+    statistics: Option<&QuerySet>,
+    record: &mut Record,
+) {
+    let samples = reference::samples_passed(case);
+    // WebGPU only guarantees zero versus non-zero, and backends are free to
+    // answer with a boolean, so an inexact count is only worth a note.
+    match harness::resolve_queries(gpu, occlusion, 1).as_deref() {
+        Some(&[passed]) if passed == samples => println!("samples passed: {passed}"),
+        Some(&[passed]) if (passed > 0) == (samples > 0) => {
+            println!("samples passed: {passed}, expected {samples} (not exact)")
+        }
+        Some(&[passed]) => {
+            println!("samples passed: {passed}, expected {samples}");
+            record.fail(format!("samples passed: {passed}, expected {samples}"));
+        }
+        _ => {
+            println!("occlusion query: no readback");
+            record.fail("occlusion query: no readback");
+        }
+    }
+
+    let Some(statistics) = statistics else {
+        return;
+    };
+    let (vertices, primitives) = if case.draw { (6, 2) } else { (0, 0) };
+    let values = harness::resolve_queries(gpu, statistics, STATISTICS.len() as u32);
+    let Some(values) = values.filter(|v| v.len() == STATISTICS.len()) else {
+        println!("pipeline statistics query: no readback");
+        record.fail("pipeline statistics query: no readback");
+        return;
+    };
+    for ((name, ty), value) in STATISTICS.iter().zip(values) {
+        // Helper invocations in partially covered quads may count as
+        // fragment invocations, so only a lower bound holds there.
+        let expected_ok = match *ty {
+            PipelineStatisticsTypes::VERTEX_SHADER_INVOCATIONS => value == vertices,
+            PipelineStatisticsTypes::FRAGMENT_SHADER_INVOCATIONS => value >= samples,
+            _ => value == primitives,
+        };
+        if expected_ok {
+            println!("{name}: {value}");
+        } else {
+            println!("{name}: {value}, unexpected");
+            record.fail(format!("{name}: {value}, unexpected"));
+        }
+    }
+}
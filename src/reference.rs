@@ -0,0 +1,363 @@
+//! CPU reference for what the GPU should produce from `DATA`.
+//!
+//! Texels are handled in memory order, so the same code covers RGBA and
+//! BGRA formats: channels 0..3 are color, channel 3 is alpha.
+
+use serde::{Deserialize, Serialize};
+use wgpu::{
+    BlendComponent, BlendFactor, BlendOperation, BlendState, ColorWrites, FilterMode, LoadOp,
+    StoreOp, TextureFormat,
+};
+
+use crate::scenario::{Read, Sampling, Scenario, SIZE};
+use crate::DATA;
+
+/// Largest per-channel difference still considered a match, to absorb
+/// rounding differences in sRGB conversion between backends.
+pub const TOLERANCE: u8 = 1;
+
+/// The texels `DATA` uploads into `tex11`, without row padding.
+pub fn texels() -> Vec<u8> {
+    DATA.chunks(256)
+        .take(SIZE as usize)
+        .flat_map(|row| &row[..(SIZE * 4) as usize])
+        .copied()
+        .collect()
+}
+
+/// What `tex01` holds before a `LoadOp::Load` pass: a gradient with varying
+/// alpha that differs from `DATA` everywhere, so blending shows.
+pub fn destination() -> Vec<u8> {
+    (0..SIZE * SIZE)
+        .flat_map(|i| {
+            let (x, y) = ((i % SIZE) as u8, (i / SIZE) as u8);
+            [x * 17, y * 17, 128, 255 - (x + y) * 8]
+        })
+        .collect()
+}
+
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// What sampling or loading a texel of `format` returns to a shader.
+pub fn decode(format: TextureFormat, texel: &[u8]) -> [f32; 4] {
+    let mut out = [0.0; 4];
+    for (i, (o, &b)) in out.iter_mut().zip(texel).enumerate() {
+        let v = b as f32 / 255.0;
+        *o = if i < 3 && format.is_srgb() {
+            srgb_to_linear(v)
+        } else {
+            v
+        };
+    }
+    out
+}
+
+/// What writing `value` from a shader stores into a texel of `format`.
+pub fn encode(format: TextureFormat, value: [f32; 4]) -> [u8; 4] {
+    let mut out = [0; 4];
+    for (i, (o, &v)) in out.iter_mut().zip(&value).enumerate() {
+        let v = v.clamp(0.0, 1.0);
+        let v = if i < 3 && format.is_srgb() {
+            linear_to_srgb(v)
+        } else {
+            v
+        };
+        *o = (v * 255.0).round() as u8;
+    }
+    out
+}
+
+/// `tex01` after the full-screen draw through `view01`.
+pub fn render(scenario: &Scenario) -> Vec<u8> {
+    if scenario.store == StoreOp::Discard {
+        return vec![0; (SIZE * SIZE * 4) as usize];
+    }
+    let source = scenario.source_view_format.unwrap_or(scenario.format);
+    let decoded: Vec<[f32; 4]> = texels().chunks(4).map(|t| decode(source, t)).collect();
+    let initial: Vec<[f32; 4]> = match scenario.load {
+        // Clear values are linear and get encoded like shader output. They
+        // name channels, so BGRA formats hold red third.
+        LoadOp::Clear(c) => {
+            let mut clear = [c.r as f32, c.g as f32, c.b as f32, c.a as f32];
+            if bgra(scenario.view_format) {
+                clear.swap(0, 2);
+            }
+            vec![clear; decoded.len()]
+        }
+        // Loaded contents are read through the view, so decoded as `view_format`.
+        LoadOp::Load => destination()
+            .chunks(4)
+            .map(|t| decode(scenario.view_format, t))
+            .collect(),
+    };
+    (0..SIZE * SIZE)
+        .flat_map(|i| {
+            if !covered(scenario, i % SIZE, i / SIZE) {
+                return encode(scenario.view_format, initial[i as usize]);
+            }
+            let src = match scenario.read {
+                Read::Load if scenario.overridden() => {
+                    overridden(&scenario.compilation.constants, decoded[i as usize])
+                }
+                Read::Load => decoded[i as usize],
+                Read::Sample(sampling) => sample(&decoded, i % SIZE, i / SIZE, sampling),
+            };
+            let value = match scenario.blend {
+                None => src,
+                Some(state) => blend(state, src, second_source(src), initial[i as usize]),
+            };
+            encode(
+                scenario.view_format,
+                mask(
+                    scenario.view_format,
+                    scenario.write_mask,
+                    value,
+                    initial[i as usize],
+                ),
+            )
+        })
+        .collect()
+}
+
+/// Whether the draw produces a fragment for texel `(x, y)`: the quad fills
+/// the viewport, and the scissor cuts that down further.
+fn covered(scenario: &Scenario, x: u32, y: u32) -> bool {
+    scenario.draw
+        && scenario.viewport.is_none_or(|r| r.contains(x, y))
+        && scenario.scissor.is_none_or(|r| r.contains(x, y))
+}
+
+/// How many samples of the single-sampled `tex01` the draw covers.
+pub fn samples_passed(scenario: &Scenario) -> u64 {
+    (0..SIZE * SIZE)
+        .filter(|i| covered(scenario, i % SIZE, i / SIZE))
+        .count() as u64
+}
+
+/// `value` where `write_mask` enables the channel, `dst` elsewhere.
+/// `ColorWrites` names channels, so BGRA formats swap the first and third.
+fn mask(
+    format: TextureFormat,
+    write_mask: ColorWrites,
+    value: [f32; 4],
+    dst: [f32; 4],
+) -> [f32; 4] {
+    let mut channels = [
+        ColorWrites::RED,
+        ColorWrites::GREEN,
+        ColorWrites::BLUE,
+        ColorWrites::ALPHA,
+    ];
+    if bgra(format) {
+        channels.swap(0, 2);
+    }
+    std::array::from_fn(|i| {
+        if write_mask.contains(channels[i]) {
+            value[i]
+        } else {
+            dst[i]
+        }
+    })
+}
+
+/// Whether `format` stores blue first, so channel names and memory order
+/// disagree.
+fn bgra(format: TextureFormat) -> bool {
+    matches!(format.remove_srgb_suffix(), TextureFormat::Bgra8Unorm)
+}
+
+/// What `SHADER_OVERRIDE` returns for a loaded texel, with unset
+/// overrides keeping their WGSL defaults.
+pub fn overridden(constants: &[(String, f64)], src: [f32; 4]) -> [f32; 4] {
+    let constant = |key: &str, default: f32| {
+        constants
+            .iter()
+            .find(|(k, _)| k == key)
+            .map_or(default, |&(_, v)| v as f32)
+    };
+    let (gain, bias, alpha) = (
+        constant("gain", 1.0),
+        constant("bias", 0.0),
+        constant("0", 1.0),
+    );
+    [
+        src[0] * gain + bias,
+        src[1] * gain + bias,
+        src[2] * gain + bias,
+        src[3] * alpha,
+    ]
+}
+
+/// The second output of `SHADER_DUAL_SOURCE` for a given first output.
+pub fn second_source(src: [f32; 4]) -> [f32; 4] {
+    [1.0 - src[0], 1.0 - src[1], 1.0 - src[2], src[3] * 0.5]
+}
+
+/// Fixed-function blending in linear space. The blend constant is never
+/// set, so it is the default of zero. Unorm targets clamp the shader's
+/// outputs to their range before they are blended.
+pub fn blend(state: BlendState, src: [f32; 4], src1: [f32; 4], dst: [f32; 4]) -> [f32; 4] {
+    let src = src.map(|c| c.clamp(0.0, 1.0));
+    let src1 = src1.map(|c| c.clamp(0.0, 1.0));
+    let mut out = [0.0; 4];
+    for (i, o) in out.iter_mut().enumerate() {
+        let component = if i < 3 { state.color } else { state.alpha };
+        *o = blend_component(component, i, src, src1, dst).clamp(0.0, 1.0);
+    }
+    out
+}
+
+fn blend_component(
+    component: BlendComponent,
+    channel: usize,
+    src: [f32; 4],
+    src1: [f32; 4],
+    dst: [f32; 4],
+) -> f32 {
+    let factor = |factor: BlendFactor| match factor {
+        BlendFactor::Zero => 0.0,
+        BlendFactor::One => 1.0,
+        BlendFactor::Src => src[channel],
+        BlendFactor::OneMinusSrc => 1.0 - src[channel],
+        BlendFactor::SrcAlpha => src[3],
+        BlendFactor::OneMinusSrcAlpha => 1.0 - src[3],
+        BlendFactor::Dst => dst[channel],
+        BlendFactor::OneMinusDst => 1.0 - dst[channel],
+        BlendFactor::DstAlpha => dst[3],
+        BlendFactor::OneMinusDstAlpha => 1.0 - dst[3],
+        BlendFactor::SrcAlphaSaturated if channel < 3 => src[3].min(1.0 - dst[3]),
+        BlendFactor::SrcAlphaSaturated => 1.0,
+        BlendFactor::Constant => 0.0,
+        BlendFactor::OneMinusConstant => 1.0,
+        BlendFactor::Src1 => src1[channel],
+        BlendFactor::OneMinusSrc1 => 1.0 - src1[channel],
+        BlendFactor::Src1Alpha => src1[3],
+        BlendFactor::OneMinusSrc1Alpha => 1.0 - src1[3],
+    };
+    let s = src[channel] * factor(component.src_factor);
+    let d = dst[channel] * factor(component.dst_factor);
+    match component.operation {
+        BlendOperation::Add => s + d,
+        BlendOperation::Subtract => s - d,
+        BlendOperation::ReverseSubtract => d - s,
+        // Min and max ignore the factors.
+        BlendOperation::Min => src[channel].min(dst[channel]),
+        BlendOperation::Max => src[channel].max(dst[channel]),
+    }
+}
+
+/// Samples already decoded texels the way `SHADER_SAMPLE` does for the
+/// fragment at `(x, y)`: filtering happens after sRGB decoding, with
+/// clamp-to-edge addressing.
+pub fn sample(decoded: &[[f32; 4]], x: u32, y: u32, sampling: Sampling) -> [f32; 4] {
+    let texel = |x: i64, y: i64| {
+        let max = SIZE as i64 - 1;
+        decoded[(y.clamp(0, max) * SIZE as i64 + x.clamp(0, max)) as usize]
+    };
+    // Texel-space coordinate of the sample point.
+    let u = x as f32 + 0.5 + sampling.offset[0];
+    let v = y as f32 + 0.5 + sampling.offset[1];
+    match sampling.filter {
+        FilterMode::Nearest => texel(u.floor() as i64, v.floor() as i64),
+        FilterMode::Linear => {
+            let (u, v) = (u - 0.5, v - 0.5);
+            let (x0, y0) = (u.floor() as i64, v.floor() as i64);
+            let (fu, fv) = (u - u.floor(), v - v.floor());
+            let mut out = [0.0; 4];
+            for (i, o) in out.iter_mut().enumerate() {
+                let top = texel(x0, y0)[i] * (1.0 - fu) + texel(x0 + 1, y0)[i] * fu;
+                let bottom = texel(x0, y0 + 1)[i] * (1.0 - fu) + texel(x0 + 1, y0 + 1)[i] * fu;
+                *o = top * (1.0 - fv) + bottom * fv;
+            }
+            out
+        }
+    }
+}
+
+/// The values the compute variant's `textureLoad` sees through
+/// `source_view_format`, one `vec4<f32>` per texel.
+pub fn loaded(scenario: &Scenario) -> Vec<[f32; 4]> {
+    let source = scenario.source_view_format.unwrap_or(scenario.format);
+    texels().chunks(4).map(|t| decode(source, t)).collect()
+}
+
+/// How far `actual` is from `expected`, texel by texel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Comparison {
+    /// Texels with any channel off by more than the tolerance.
+    pub mismatched: usize,
+    /// Largest per-channel difference anywhere.
+    pub max_diff: u8,
+}
+
+impl Comparison {
+    pub fn matches(&self) -> bool {
+        self.mismatched == 0
+    }
+}
+
+pub fn compare(expected: &[u8], actual: &[u8]) -> Comparison {
+    compare_within(expected, actual, TOLERANCE)
+}
+
+/// [`compare`] with a caller-chosen tolerance. A readback of the wrong
+/// length mismatches everywhere.
+pub fn compare_within(expected: &[u8], actual: &[u8], tolerance: u8) -> Comparison {
+    if expected.len() != actual.len() {
+        return Comparison {
+            mismatched: expected.len().max(actual.len()).div_ceil(4),
+            max_diff: u8::MAX,
+        };
+    }
+    let mut comparison = Comparison::default();
+    for (e, a) in expected.chunks(4).zip(actual.chunks(4)) {
+        let diff = e.iter().zip(a).map(|(e, a)| e.abs_diff(*a)).max().unwrap();
+        comparison.max_diff = comparison.max_diff.max(diff);
+        if diff > tolerance {
+            comparison.mismatched += 1;
+        }
+    }
+    comparison
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_within_tolerance() {
+        let expected = [10, 20, 30, 255, 0, 0, 0, 0];
+        let actual = [12, 20, 30, 255, 0, 0, 9, 0];
+        assert_eq!(
+            compare_within(&expected, &actual, 2),
+            Comparison {
+                mismatched: 1,
+                max_diff: 9
+            }
+        );
+        assert!(compare_within(&expected, &actual, 9).matches());
+    }
+
+    #[test]
+    fn compare_short_readback() {
+        let expected = [0; 16];
+        let comparison = compare_within(&expected, &expected[..8], u8::MAX);
+        assert_eq!(comparison.mismatched, 4);
+        assert_eq!(comparison.max_diff, u8::MAX);
+        assert!(!compare_within(&expected, &[], u8::MAX).matches());
+    }
+}
@@ -0,0 +1,89 @@
+//! `regions`: partial draws into `view01` through per-channel write masks,
+//! viewports and scissor rectangles, against a reference that predicts
+//! which texels and channels keep the clear value.
+
+use wgpu::{Color, ColorWrites, LoadOp};
+
+use crate::harness::{self, Gpu};
+use crate::reference;
+use crate::results::{self, Record};
+use crate::scenario::{Rect, Scenario};
+
+/// Clear color for every case: mid-range in each channel, so retained
+/// channels stand out from written ones.
+const CLEAR: Color = Color {
+    r: 0.2,
+    g: 0.9,
+    b: 0.4,
+    a: 0.6,
+};
+
+fn rect(x: u32, y: u32, width: u32, height: u32) -> Rect {
+    Rect {
+        x,
+        y,
+        width,
+        height,
+    }
+}
+
+/// Write mask, viewport and scissor of each case.
+type Case = (&'static str, ColorWrites, Option<Rect>, Option<Rect>);
+
+fn cases() -> Vec<Case> {
+    let all = ColorWrites::ALL;
+    vec![
+        ("mask red", ColorWrites::RED, None, None),
+        ("mask green", ColorWrites::GREEN, None, None),
+        ("mask blue", ColorWrites::BLUE, None, None),
+        ("mask alpha", ColorWrites::ALPHA, None, None),
+        ("mask color", ColorWrites::COLOR, None, None),
+        (
+            "mask red|alpha",
+            ColorWrites::RED | ColorWrites::ALPHA,
+            None,
+            None,
+        ),
+        ("mask none", ColorWrites::empty(), None, None),
+        ("viewport 3,5 7x9", all, Some(rect(3, 5, 7, 9)), None),
+        ("viewport 0,0 1x1", all, Some(rect(0, 0, 1, 1)), None),
+        ("viewport 11,2 5x13", all, Some(rect(11, 2, 5, 13)), None),
+        ("scissor 1,1 13x3", all, None, Some(rect(1, 1, 13, 3))),
+        ("scissor 15,15 1x1", all, None, Some(rect(15, 15, 1, 1))),
+        ("scissor 4,0 3x16", all, None, Some(rect(4, 0, 3, 16))),
+        (
+            "viewport 2,2 9x9, scissor 7,5 8x5",
+            all,
+            Some(rect(2, 2, 9, 9)),
+            Some(rect(7, 5, 8, 5)),
+        ),
+        (
+            "mask green|blue, scissor 5,9 6x7",
+            ColorWrites::GREEN | ColorWrites::BLUE,
+            None,
+            Some(rect(5, 9, 6, 7)),
+        ),
+    ]
+}
+
+/// Returns `false` if any case raised errors or disagreed with the reference.
+pub async fn run(gpu: &Gpu, scenario: &Scenario) -> bool {
+    let mut ok = true;
+    for (name, write_mask, viewport, scissor) in cases() {
+        let case = Scenario {
+            write_mask,
+            viewport,
+            scissor,
+            load: LoadOp::Clear(CLEAR),
+            ..scenario.clone()
+        };
+        println!("== {name}");
+        let outcome = harness::run(gpu, &case).await;
+        outcome.print();
+        let mut record = Record::new(gpu, name, &case);
+        record.add_outcome(&outcome);
+        record.compare(&outcome, &reference::render(&case), reference::TOLERANCE);
+        ok &= results::push(record);
+    }
+    ok
+}
@@ -0,0 +1,342 @@
+//! `report [dir] [--tar]`: run the scenario and, if it fails, gather what an
+//! upstream wgpu issue needs into `dir` (default `target/report`), plus
+//! `dir.tar` with `--tar`:
+//!
+//! - `issue.md`, an issue body laid out like wgpu's bug template;
+//! - `scenario.txt` and `result.json`, what ran and how it went;
+//! - `repro/`, a standalone Cargo project running the same scenario;
+//! - `trace/`, the wgpu API trace, when built with the `trace` feature;
+//! - `adapter.txt`, `features.txt` and `limits.txt`;
+//! - `instance.txt`, the [`instance`] settings, validation among them;
+//! - `errors.txt`, each error with what its step logged;
+//! - `expected.png`, `actual.png` and `diff.png`, scaled up for viewing.
+
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+use wgpu::TextureFormat;
+
+use crate::cli::Args;
+use crate::harness::{self, Gpu, Outcome};
+use crate::instance;
+use crate::reference;
+use crate::repro;
+use crate::results::{self, Record};
+use crate::scenario::{Scenario, SIZE};
+
+/// Images are blown up from `SIZE`x`SIZE` so single texels are visible.
+const SCALE: u32 = 16;
+/// How much per-channel differences are amplified in `diff.png`.
+const DIFF_GAIN: u8 = 16;
+
+/// Returns `false` if the scenario failed or the bundle couldn't be
+/// written. A passing run writes nothing.
+pub async fn run(args: &Args, scenario: &Scenario) -> bool {
+    let dir = Path::new(args.positional(0).unwrap_or("target/report"));
+    let instance = instance::new(&scenario.instance);
+    let adapter = harness::request_adapter(&instance).await;
+    let trace = dir.join("trace");
+    let traced = cfg!(feature = "trace");
+    if traced {
+        let _ = fs::remove_dir_all(&trace);
+        if let Err(err) = fs::create_dir_all(&trace) {
+            println!("failed to create {}: {err}", trace.display());
+            return false;
+        }
+    } else {
+        println!("built without `trace`, no API trace recorded");
+    }
+    let gpu = harness::open_traced(
+        instance,
+        &scenario.instance,
+        adapter,
+        traced.then_some(&*trace),
+    )
+    .await;
+    println!("{:?}", gpu.adapter.get_info());
+
+    let outcome = harness::run(&gpu, scenario).await;
+    outcome.print();
+    let mut record = Record::new(&gpu, "report", scenario);
+    record.add_outcome(&outcome);
+    let expected = reference::render(scenario);
+    record.compare(&outcome, &expected, reference::TOLERANCE);
+    if record.reasons().is_empty() {
+        println!("scenario passed, nothing to report");
+        return results::push(record);
+    }
+
+    let mut written = match write(dir, &gpu, scenario, &outcome, &expected, &mut record) {
+        Ok(()) => {
+            println!("wrote {}", dir.display());
+            true
+        }
+        Err(err) => {
+            println!("failed to write {}: {err}", dir.display());
+            false
+        }
+    };
+    if written && args.flag("tar") {
+        let tarball = dir.with_extension("tar");
+        match archive(dir, &tarball) {
+            Ok(()) => println!("wrote {}", tarball.display()),
+            Err(err) => {
+                println!("failed to write {}: {err}", tarball.display());
+                written = false;
+            }
+        }
+    }
+    results::push(record) && written
+}
+
+fn write(
+    dir: &Path,
+    gpu: &Gpu,
+    scenario: &Scenario,
+    outcome: &Outcome,
+    expected: &[u8],
+    record: &mut Record,
+) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let info = gpu.adapter.get_info();
+    fs::write(dir.join("scenario.txt"), format!("{scenario:#?}\n"))?;
+    fs::write(dir.join("adapter.txt"), format!("{info:#?}\n"))?;
+    let settings: String = record
+        .instance
+        .iter()
+        .map(|(name, value)| format!("{name}: {value}\n"))
+        .collect();
+    fs::write(dir.join("instance.txt"), settings)?;
+    fs::write(
+        dir.join("features.txt"),
+        format!(
+            "adapter: {:?}\ndevice: {:?}\n",
+            gpu.adapter.features(),
+            gpu.device.features()
+        ),
+    )?;
+    fs::write(
+        dir.join("limits.txt"),
+        format!("{:#?}\n", gpu.device.limits()),
+    )?;
+    let errors: String = outcome
+        .errors
+        .iter()
+        .map(|err| {
+            let logs: String = err.logs.iter().map(|line| format!("  {line}\n")).collect();
+            format!("in {}: {}\n{logs}", err.step, err.message)
+        })
+        .collect();
+    fs::write(dir.join("errors.txt"), &errors)?;
+
+    let repro_dir = dir.join("repro");
+    let repro = repro::main_rs(scenario);
+    match &repro {
+        Ok(main_rs) => {
+            fs::create_dir_all(repro_dir.join("src"))?;
+            fs::write(repro_dir.join("Cargo.toml"), repro::manifest())?;
+            fs::write(repro_dir.join("src/main.rs"), main_rs)?;
+        }
+        Err(reason) => println!("no standalone repro: {reason}"),
+    }
+
+    let mut images = vec![("expected.png", expected.to_vec())];
+    if let Some(actual) = &outcome.pixels {
+        images.push(("diff.png", diff(expected, actual)));
+        images.push(("actual.png", actual.clone()));
+    }
+    for (name, texels) in &images {
+        write_png(&dir.join(name), scenario.format, texels)?;
+    }
+    let traced = dir.join("trace").join("trace.ron").exists();
+    if !traced && cfg!(feature = "trace") {
+        println!("wgpu recorded no API trace");
+        let _ = fs::remove_dir(dir.join("trace"));
+    }
+
+    for entry in fs::read_dir(dir)? {
+        record.artifacts.push(entry?.path().display().to_string());
+    }
+    record.artifacts.sort();
+    fs::write(
+        dir.join("result.json"),
+        serde_json::to_string_pretty(record)? + "\n",
+    )?;
+    let images: Vec<_> = images.iter().map(|(name, _)| *name).collect();
+    fs::write(
+        dir.join("issue.md"),
+        issue(
+            gpu,
+            scenario,
+            record,
+            &errors,
+            repro.as_ref(),
+            &images,
+            traced,
+        ),
+    )?;
+    Ok(())
+}
+
+/// Per-channel differences, amplified, on opaque black. Texels missing from
+/// a short `actual` show up white.
+pub fn diff(expected: &[u8], actual: &[u8]) -> Vec<u8> {
+    expected
+        .chunks(4)
+        .enumerate()
+        .flat_map(|(i, e)| {
+            let Some(a) = actual.get(i * 4..i * 4 + 4) else {
+                return [255; 4];
+            };
+            let d = |i: usize| e[i].abs_diff(a[i]).saturating_mul(DIFF_GAIN);
+            // Alpha differences show up as white, since alpha itself can't.
+            let alpha = d(3);
+            [d(0).max(alpha), d(1).max(alpha), d(2).max(alpha), 255]
+        })
+        .collect()
+}
+
+/// `SIZE`x`SIZE` texels of `format` as an RGBA PNG, scaled up by `SCALE`.
+/// Texels missing from a short `texels` are transparent black, and show
+/// up white in [`diff`].
+pub fn write_png(path: &Path, format: TextureFormat, texels: &[u8]) -> io::Result<()> {
+    let size = SIZE * SCALE;
+    let bgra = matches!(
+        format,
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb
+    );
+    let mut data = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let texel = ((y / SCALE) * SIZE + x / SCALE) as usize * 4;
+            let [r, g, b, a]: [u8; 4] = texels
+                .get(texel..texel + 4)
+                .map_or([0; 4], |t| t.try_into().unwrap());
+            data.extend(if bgra { [b, g, r, a] } else { [r, g, b, a] });
+        }
+    }
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), size, size);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&data)?;
+    Ok(())
+}
+
+fn archive(dir: &Path, tarball: &Path) -> io::Result<()> {
+    let mut builder = tar::Builder::new(BufWriter::new(File::create(tarball)?));
+    let name = dir.file_name().unwrap_or(dir.as_os_str());
+    builder.append_dir_all(name, dir)?;
+    builder.into_inner()?;
+    Ok(())
+}
+
+/// An issue body following the sections of wgpu's bug report template.
+fn issue(
+    gpu: &Gpu,
+    scenario: &Scenario,
+    record: &Record,
+    errors: &str,
+    repro: Result<&String, &String>,
+    images: &[&str],
+    traced: bool,
+) -> String {
+    let info = gpu.adapter.get_info();
+    let mut md = String::new();
+    let _ = writeln!(md, "## Description\n");
+    let _ = writeln!(
+        md,
+        "Rendering `{:?}` data from a `{:?}` texture into a `{:?}` view of a `{:?}` texture \
+         does not produce the expected output on `{}` ({:?}).\n",
+        scenario.source_view_format.unwrap_or(scenario.format),
+        scenario.format,
+        scenario.view_format,
+        scenario.format,
+        info.name,
+        info.backend,
+    );
+    for reason in record.reasons() {
+        let _ = writeln!(md, "- {}", reason.lines().next().unwrap_or_default());
+    }
+
+    let _ = writeln!(md, "\n## Repro steps\n");
+    match repro {
+        Ok(main_rs) => {
+            let _ = writeln!(
+                md,
+                "`cargo run` in a project with this `Cargo.toml`:\n\n```toml\n{}```\n",
+                repro::manifest()
+            );
+            let _ = writeln!(
+                md,
+                "<details><summary><code>src/main.rs</code></summary>\n\n```rust\n{main_rs}```\n\n</details>\n"
+            );
+        }
+        Err(reason) => {
+            let _ = writeln!(
+                md,
+                "No standalone repro ({reason}). Scenario:\n\n```\n{scenario:#?}\n```\n"
+            );
+        }
+    }
+
+    let _ = writeln!(md, "## Expected vs observed behavior\n");
+    match record.comparison {
+        Some(comparison) => {
+            let _ = writeln!(
+                md,
+                "Expected output matching a CPU reference within {} per channel; {} of {} \
+                 texels differ, by up to {}.\n",
+                record.tolerance.unwrap_or(reference::TOLERANCE),
+                comparison.mismatched,
+                SIZE * SIZE,
+                comparison.max_diff,
+            );
+        }
+        None => {
+            let _ = writeln!(
+                md,
+                "Expected a readback of the rendered texture; got none.\n"
+            );
+        }
+    }
+    if !errors.is_empty() {
+        let _ = writeln!(md, "Validation errors:\n\n```\n{errors}```\n");
+    }
+
+    let _ = writeln!(md, "## Extra materials\n");
+    let _ = writeln!(
+        md,
+        "Attached: {}{}.\n",
+        images
+            .iter()
+            .map(|name| format!("`{name}`"))
+            .collect::<Vec<_>>()
+            .join(", "),
+        if traced {
+            ", and the API trace in `trace/`"
+        } else {
+            ""
+        },
+    );
+
+    let _ = writeln!(md, "## Platform\n");
+    let _ = writeln!(md, "- wgpu: `{}`", record.wgpu_revision);
+    let _ = writeln!(
+        md,
+        "- adapter: {} ({:?}, {:?})",
+        info.name, info.backend, info.device_type
+    );
+    let _ = writeln!(md, "- driver: {} {}", info.driver, info.driver_info);
+    for (name, value) in &record.instance {
+        let _ = writeln!(md, "- {name}: `{value}`");
+    }
+    let _ = writeln!(
+        md,
+        "- OS: {} {}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    md
+}
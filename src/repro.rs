@@ -0,0 +1,378 @@
+//! Standalone repro generation: a single-file wgpu program, with its own
+//! `Cargo.toml`, that runs a [`Scenario`] the way [`harness::run`] does and
+//! checks the output against the CPU reference, for pasting into upstream
+//! issues.
+//!
+//! Only the plain render path is covered: scenarios sampling `tex11`, with
+//! an explicit layout, dual-source blending, `LoadOp::Load` or a destroyed
+//! device are refused rather than approximated.
+//!
+//! [`harness::run`]: crate::harness::run
+
+use std::fmt::Write as _;
+
+use wgpu::{BlendComponent, LoadOp, TextureFormat};
+
+use crate::harness::ROW_PITCH;
+use crate::reference;
+use crate::results::WGPU_REVISION;
+use crate::scenario::{Layout, Read, Scenario, SIZE};
+use crate::shaders;
+use crate::DATA;
+
+/// `Cargo.toml` of the repro, on the same wgpu as this build.
+pub fn manifest() -> String {
+    let wgpu = if WGPU_REVISION.len() == 40 && WGPU_REVISION.bytes().all(|b| b.is_ascii_hexdigit())
+    {
+        format!("{{ git = \"https://github.com/gfx-rs/wgpu\", rev = \"{WGPU_REVISION}\" }}")
+    } else {
+        format!("\"{WGPU_REVISION}\"")
+    };
+    format!(
+        "[package]\nname = \"wgpu-repro\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+         [dependencies]\npollster = \"0.3\"\nwgpu = {wgpu}\n"
+    )
+}
+
+/// `src/main.rs` of the repro, or what about `scenario` it can't express.
+pub fn main_rs(scenario: &Scenario) -> Result<String, String> {
+    let mut unsupported = Vec::new();
+    if scenario.read != Read::Load {
+        unsupported.push("sampling reads");
+    }
+    if scenario.layout != Layout::Auto {
+        unsupported.push("explicit layouts");
+    }
+    if scenario.dual_source() {
+        unsupported.push("dual-source blending");
+    }
+    if scenario.load == LoadOp::Load {
+        unsupported.push("LoadOp::Load");
+    }
+    if scenario.destroy.is_some() {
+        unsupported.push("device destruction");
+    }
+    if !unsupported.is_empty() {
+        return Err(format!(
+            "not supported in repros: {}",
+            unsupported.join(", ")
+        ));
+    }
+
+    let compilation = &scenario.compilation;
+    let mut pass = String::new();
+    if let Some(v) = scenario.viewport {
+        let _ = writeln!(
+            pass,
+            "        pass.set_viewport({}.0, {}.0, {}.0, {}.0, 0.0, 1.0);",
+            v.x, v.y, v.width, v.height
+        );
+    }
+    if let Some(s) = scenario.scissor {
+        let _ = writeln!(
+            pass,
+            "        pass.set_scissor_rect({}, {}, {}, {});",
+            s.x, s.y, s.width, s.height
+        );
+    }
+    if scenario.draw {
+        pass.push_str("        pass.draw(0..6, 0..1);\n");
+    }
+    let LoadOp::Clear(clear) = scenario.load else {
+        unreachable!("refused above");
+    };
+    let fragment = if scenario.overridden() {
+        shaders::SHADER_OVERRIDE
+    } else {
+        shaders::SHADER11
+    };
+    let constants: Vec<_> = compilation
+        .constants
+        .iter()
+        .map(|(key, value)| format!("(\"{key}\".to_string(), {value:?})"))
+        .collect();
+
+    Ok(TEMPLATE
+        .replace("$SCENARIO", &format!("{scenario:?}"))
+        .replace("$INSTANCE", &scenario.instance.descriptor_expr())
+        .replace("$FORMAT", &format(scenario.format))
+        .replace(
+            "$VIEW_FORMATS",
+            &view_formats(scenario.format, Some(scenario.view_format)),
+        )
+        .replace(
+            "$SOURCE_VIEW_FORMATS",
+            &view_formats(scenario.format, scenario.source_view_format),
+        )
+        .replace("$VIEW_FORMAT", &format(scenario.view_format))
+        .replace(
+            "$SOURCE_VIEW_FORMAT",
+            &match scenario.source_view_format {
+                Some(format) => format!("Some({})", self::format(format)),
+                None => "None".to_string(),
+            },
+        )
+        .replace("$CONSTANTS", &constants.join(", "))
+        .replace(
+            "$ZERO_INITIALIZE",
+            &compilation.zero_initialize_workgroup_memory.to_string(),
+        )
+        .replace(
+            "$VERTEX_PULLING",
+            &compilation.vertex_pulling_transform.to_string(),
+        )
+        .replace(
+            "$BLEND",
+            &match scenario.blend {
+                Some(blend) => format!(
+                    "Some(BlendState {{ color: {}, alpha: {} }})",
+                    component(blend.color),
+                    component(blend.alpha)
+                ),
+                None => "None".to_string(),
+            },
+        )
+        .replace("$WRITE_MASK", &scenario.write_mask.bits().to_string())
+        .replace("$CLEAR", &format!("{clear:?}"))
+        .replace("$STORE", &format!("{:?}", scenario.store))
+        .replace("$PASS\n", &pass)
+        .replace("$TOLERANCE", &reference::TOLERANCE.to_string())
+        .replace("$ROW_PITCH", &ROW_PITCH.to_string())
+        .replace("$SIZE", &SIZE.to_string())
+        .replace("$SHADER01", shaders::SHADER01)
+        .replace("$SHADER11", fragment)
+        .replace("$DATA", &bytes(&DATA))
+        .replace("$EXPECTED_LEN", &(SIZE * SIZE * 4).to_string())
+        .replace("$EXPECTED", &bytes(&reference::render(scenario))))
+}
+
+fn format(format: TextureFormat) -> String {
+    format!("TextureFormat::{format:?}")
+}
+
+fn view_formats(format: TextureFormat, view_format: Option<TextureFormat>) -> String {
+    match view_format {
+        Some(view_format) if view_format != format => self::format(view_format),
+        _ => String::new(),
+    }
+}
+
+fn component(component: BlendComponent) -> String {
+    format!(
+        "BlendComponent {{ src_factor: BlendFactor::{:?}, dst_factor: BlendFactor::{:?}, operation: BlendOperation::{:?} }}",
+        component.src_factor, component.dst_factor, component.operation
+    )
+}
+
+/// `bytes` as the elements of an array literal, wrapped like rustfmt would.
+fn bytes(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for line in bytes.chunks(24) {
+        let line: Vec<_> = line.iter().map(u8::to_string).collect();
+        let _ = write!(out, "\n    {},", line.join(", "));
+    }
+    out.push('\n');
+    out
+}
+
+const TEMPLATE: &str = r##"//! Generated by `wgpu-problem report` from
+//! $SCENARIO
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use wgpu::*;
+
+const SIZE: u32 = $SIZE;
+const ROW_PITCH: u32 = $ROW_PITCH;
+
+fn main() {
+    pollster::block_on(run());
+}
+
+async fn run() {
+    let instance = Instance::new($INSTANCE);
+    let adapter = instance
+        .request_adapter(&RequestAdapterOptions::default())
+        .await
+        .expect("no adapter");
+    println!("{:?}", adapter.get_info());
+    let (device, queue) = adapter
+        .request_device(
+            &DeviceDescriptor {
+                label: None,
+                required_features: Features::empty(),
+                required_limits: Limits::default(),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    device.on_uncaptured_error(Box::new(|err| println!("ERROR: {err}")));
+
+    let size = Extent3d {
+        width: SIZE,
+        height: SIZE,
+        depth_or_array_layers: 1,
+    };
+    let tex01 = device.create_texture(&TextureDescriptor {
+        label: None,
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: $FORMAT,
+        usage: TextureUsages::COPY_SRC | TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[$VIEW_FORMATS],
+    });
+    let tex11 = device.create_texture(&TextureDescriptor {
+        label: None,
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: $FORMAT,
+        usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
+        view_formats: &[$SOURCE_VIEW_FORMATS],
+    });
+    queue.write_texture(
+        tex11.as_image_copy(),
+        &DATA,
+        ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(ROW_PITCH),
+            rows_per_image: Some(SIZE),
+        },
+        size,
+    );
+    let view01 = tex01.create_view(&TextureViewDescriptor {
+        format: Some($VIEW_FORMAT),
+        ..Default::default()
+    });
+    let view11 = tex11.create_view(&TextureViewDescriptor {
+        format: $SOURCE_VIEW_FORMAT,
+        ..Default::default()
+    });
+
+    let shader01 = device.create_shader_module(ShaderModuleDescriptor {
+        label: None,
+        source: ShaderSource::Wgsl(Cow::Borrowed(SHADER01)),
+    });
+    let shader11 = device.create_shader_module(ShaderModuleDescriptor {
+        label: None,
+        source: ShaderSource::Wgsl(Cow::Borrowed(SHADER11)),
+    });
+    let constants: HashMap<String, f64> = HashMap::from([$CONSTANTS]);
+    let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: None,
+        layout: None,
+        vertex: VertexState {
+            module: &shader01,
+            entry_point: "main",
+            compilation_options: PipelineCompilationOptions {
+                constants: &HashMap::new(),
+                zero_initialize_workgroup_memory: $ZERO_INITIALIZE,
+                vertex_pulling_transform: $VERTEX_PULLING,
+            },
+            buffers: &[],
+        },
+        primitive: PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: MultisampleState::default(),
+        fragment: Some(FragmentState {
+            module: &shader11,
+            entry_point: "main",
+            compilation_options: PipelineCompilationOptions {
+                constants: &constants,
+                zero_initialize_workgroup_memory: $ZERO_INITIALIZE,
+                vertex_pulling_transform: $VERTEX_PULLING,
+            },
+            targets: &[Some(ColorTargetState {
+                format: $VIEW_FORMAT,
+                blend: $BLEND,
+                write_mask: ColorWrites::from_bits_truncate($WRITE_MASK),
+            })],
+        }),
+        multiview: None,
+        cache: None,
+    });
+    let bind_group = device.create_bind_group(&BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[BindGroupEntry {
+            binding: 0,
+            resource: BindingResource::TextureView(&view11),
+        }],
+    });
+
+    let readback = device.create_buffer(&BufferDescriptor {
+        label: None,
+        size: (ROW_PITCH * SIZE) as u64,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
+    {
+        let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &view01,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear($CLEAR),
+                    store: StoreOp::$STORE,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+$PASS
+    }
+    encoder.copy_texture_to_buffer(
+        tex01.as_image_copy(),
+        ImageCopyBuffer {
+            buffer: &readback,
+            layout: ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(ROW_PITCH),
+                rows_per_image: Some(SIZE),
+            },
+        },
+        size,
+    );
+    queue.submit([encoder.finish()]);
+    readback
+        .slice(..)
+        .map_async(MapMode::Read, |result| result.unwrap());
+    device.poll(Maintain::Wait);
+
+    let padded = readback.slice(..).get_mapped_range();
+    let mut mismatched = 0;
+    for y in 0..SIZE as usize {
+        for x in 0..SIZE as usize {
+            let actual = &padded[y * ROW_PITCH as usize + x * 4..][..4];
+            let expected = &EXPECTED[(y * SIZE as usize + x) * 4..][..4];
+            if actual.iter().zip(expected).any(|(a, e)| a.abs_diff(*e) > $TOLERANCE) {
+                if mismatched == 0 {
+                    println!("first mismatch at ({x}, {y}): expected {expected:?}, got {actual:?}");
+                }
+                mismatched += 1;
+            }
+        }
+    }
+    println!("{mismatched} of {} texels differ from the expected output", SIZE * SIZE);
+}
+
+const SHADER01: &str = r#"$SHADER01"#;
+
+const SHADER11: &str = r#"$SHADER11"#;
+
+/// Source texels, `ROW_PITCH` bytes per row.
+const DATA: [u8; 4096] = [$DATA];
+
+/// Tightly packed texels the render should leave in `tex01`.
+const EXPECTED: [u8; $EXPECTED_LEN] = [$EXPECTED];
+"##;
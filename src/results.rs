@@ -0,0 +1,634 @@
+//! Machine-readable results: every case a mode checks becomes a [`Record`],
+//! written on exit as JSON lines (`--json=PATH`, appended to) and as JUnit
+//! XML (`--junit=PATH`, one test suite per adapter), and appended to the
+//! [`history`](crate::history).
+//!
+//! Records go to a process-wide sink, like the `println!` lines they
+//! accompany, so modes don't have to thread a collector through every case.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write as _};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cli::Args;
+use crate::expectations::{self, Expectation};
+use crate::harness::{Gpu, Outcome, StepError};
+use crate::history;
+use crate::instance;
+use crate::reference::{self, Comparison};
+use crate::scenario::Scenario;
+
+/// The wgpu rev, or version, `Cargo.toml` selects; see `build.rs`.
+pub const WGPU_REVISION: &str = env!("WGPU_REVISION");
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Record {
+    /// Command the case ran under, e.g. `blend`.
+    pub mode: String,
+    pub case: String,
+    /// Every scenario field, `Debug`-formatted.
+    pub parameters: BTreeMap<String, String>,
+    pub adapter: Option<AdapterSummary>,
+    /// How the instance was created; see [`instance`](crate::instance).
+    #[serde(default)]
+    pub instance: BTreeMap<String, String>,
+    pub wgpu_revision: String,
+    /// Seconds since the Unix epoch.
+    pub time: u64,
+    pub errors: Vec<ErrorRecord>,
+    pub comparison: Option<Comparison>,
+    /// Tolerance `comparison` was made with.
+    pub tolerance: Option<u8>,
+    /// Why the case failed, other than errors and the comparison.
+    pub failures: Vec<String>,
+    /// Files the case wrote.
+    pub artifacts: Vec<String>,
+    pub timings: Vec<Timing>,
+    /// Why the case didn't run, if it didn't.
+    pub skipped: Option<String>,
+    pub status: Status,
+    /// The expectation (`path:line`) this case is known to fail by, if any.
+    #[serde(default)]
+    pub expected: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AdapterSummary {
+    pub name: String,
+    pub backend: String,
+    pub device_type: String,
+    pub driver: String,
+    pub driver_info: String,
+    pub vendor: u32,
+    pub device: u32,
+}
+
+impl AdapterSummary {
+    pub fn of(gpu: &Gpu) -> Self {
+        let info = gpu.adapter.get_info();
+        AdapterSummary {
+            name: info.name,
+            backend: format!("{:?}", info.backend),
+            device_type: format!("{:?}", info.device_type),
+            driver: info.driver,
+            driver_info: info.driver_info,
+            vendor: info.vendor,
+            device: info.device,
+        }
+    }
+
+    /// `name (backend)`, how suites and reports refer to the adapter.
+    pub fn label(&self) -> String {
+        format!("{} ({})", self.name, self.backend)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ErrorRecord {
+    pub step: String,
+    pub message: String,
+    #[serde(default)]
+    pub logs: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Timing {
+    pub step: String,
+    pub seconds: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+impl Record {
+    pub fn new(gpu: &Gpu, case: impl Into<String>, scenario: &Scenario) -> Self {
+        Record {
+            parameters: parameters(scenario),
+            adapter: Some(AdapterSummary::of(gpu)),
+            instance: gpu.settings.summary_on(gpu.adapter.get_info().backend),
+            ..Record::bare(case.into())
+        }
+    }
+
+    /// A record tied to no adapter or scenario, only to the instance
+    /// settings of the command line.
+    pub fn bare(case: String) -> Self {
+        Record {
+            mode: String::new(),
+            case,
+            parameters: BTreeMap::new(),
+            adapter: None,
+            instance: instance::configured().summary(),
+            wgpu_revision: WGPU_REVISION.to_string(),
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            errors: Vec::new(),
+            comparison: None,
+            tolerance: None,
+            failures: Vec::new(),
+            artifacts: Vec::new(),
+            timings: Vec::new(),
+            skipped: None,
+            status: Status::Passed,
+            expected: None,
+        }
+    }
+
+    /// Takes over the errors and timings of `outcome`.
+    pub fn add_outcome(&mut self, outcome: &Outcome) {
+        self.add_errors(&outcome.errors);
+        self.timings
+            .extend(outcome.timings.iter().map(|&(step, duration)| Timing {
+                step: step.to_string(),
+                seconds: duration.as_secs_f64(),
+            }));
+    }
+
+    pub fn add_errors(&mut self, errors: &[StepError]) {
+        self.errors.extend(errors.iter().map(|err| ErrorRecord {
+            step: err.step.to_string(),
+            message: err.message.clone(),
+            logs: err.logs.clone(),
+        }));
+    }
+
+    /// Compares the pixels of `outcome` with `expected` and prints the result.
+    pub fn compare(&mut self, outcome: &Outcome, expected: &[u8], tolerance: u8) {
+        match &outcome.pixels {
+            Some(pixels) => {
+                let comparison = reference::compare_within(expected, pixels, tolerance);
+                println!("vs reference: {comparison:?}");
+                self.comparison = Some(comparison);
+                self.tolerance = Some(tolerance);
+            }
+            None => self.fail("no readback"),
+        }
+    }
+
+    pub fn fail(&mut self, reason: impl Into<String>) {
+        self.failures.push(reason.into());
+    }
+
+    /// Everything that makes the case fail, one line each.
+    pub fn reasons(&self) -> Vec<String> {
+        let mut reasons: Vec<_> = self
+            .errors
+            .iter()
+            .map(|err| format!("error in {}: {}", err.step, err.message))
+            .collect();
+        if let Some(comparison) = self.comparison.filter(|c| !c.matches()) {
+            reasons.push(format!(
+                "{} texels differ from the reference, by up to {}",
+                comparison.mismatched, comparison.max_diff
+            ));
+        }
+        reasons.extend(self.failures.iter().cloned());
+        reasons
+    }
+
+    fn seconds(&self) -> f64 {
+        self.timings.iter().map(|timing| timing.seconds).sum()
+    }
+}
+
+fn parameters(scenario: &Scenario) -> BTreeMap<String, String> {
+    // Destructured so a new field can't be left out of the results.
+    let Scenario {
+        format,
+        view_format,
+        source_view_format,
+        read,
+        layout,
+        blend,
+        load,
+        store,
+        draw,
+        write_mask,
+        viewport,
+        scissor,
+        compilation,
+        destroy,
+        // In `Record::instance`, as the `Gpu` it ran on applied them.
+        instance: _,
+    } = scenario;
+    [
+        ("format", format!("{format:?}")),
+        ("view_format", format!("{view_format:?}")),
+        ("source_view_format", format!("{source_view_format:?}")),
+        ("read", format!("{read:?}")),
+        ("layout", format!("{layout:?}")),
+        ("blend", format!("{blend:?}")),
+        ("load", format!("{load:?}")),
+        ("store", format!("{store:?}")),
+        ("draw", format!("{draw:?}")),
+        ("write_mask", format!("{write_mask:?}")),
+        ("viewport", format!("{viewport:?}")),
+        ("scissor", format!("{scissor:?}")),
+        ("compilation", format!("{compilation:?}")),
+        ("destroy", format!("{destroy:?}")),
+    ]
+    .into_iter()
+    .map(|(key, value)| (key.to_string(), value))
+    .collect()
+}
+
+struct Sink {
+    mode: String,
+    expectations: Option<Vec<Expectation>>,
+    records: Vec<Record>,
+}
+
+static SINK: Mutex<Sink> = Mutex::new(Sink {
+    mode: String::new(),
+    expectations: None,
+    records: Vec::new(),
+});
+
+/// Names the mode later records belong to, and reads the expectations up
+/// front so a malformed file fails before anything runs.
+pub fn start(args: &Args) {
+    let mut sink = SINK.lock().unwrap();
+    sink.mode = args.command.as_deref().unwrap_or("run").to_string();
+    sink.expectations = expectations::load(args);
+}
+
+/// Settles the status of `record` and keeps it for the output files.
+/// Returns `false` if the case failed.
+pub fn push(mut record: Record) -> bool {
+    record.status = if record.reasons().is_empty() {
+        Status::Passed
+    } else {
+        Status::Failed
+    };
+    let passed = record.status == Status::Passed;
+    let mut sink = SINK.lock().unwrap();
+    record.mode = sink.mode.clone();
+    sink.records.push(record);
+    passed
+}
+
+pub fn skip(mut record: Record, reason: impl Into<String>) {
+    let mut sink = SINK.lock().unwrap();
+    record.mode = sink.mode.clone();
+    record.skipped = Some(reason.into());
+    record.status = Status::Skipped;
+    sink.records.push(record);
+}
+
+/// Judges the run against the expectations, writes the output files `args`
+/// asks for and appends to the history. See [`settle`] for how `ok` is
+/// recorded.
+///
+/// Returns `false` if the run failed (unexpectedly, given expectations) or
+/// a file couldn't be written.
+pub fn finish(args: &Args, ok: bool) -> bool {
+    let mut sink = SINK.lock().unwrap();
+    let Sink {
+        mode,
+        expectations,
+        records,
+    } = &mut *sink;
+    settle(mode, records, ok);
+    let ok = match expectations {
+        Some(expectations) => expectations::apply(expectations, records),
+        None => ok,
+    };
+    let mut written = true;
+    if let Some(path) = args.value("json") {
+        written &= report_write(path, append_json_lines(Path::new(path), &sink.records));
+    }
+    if let Some(path) = args.value("junit") {
+        written &= report_write(path, fs::write(path, junit(&sink.records)));
+    }
+    if !args.flag("no-history") {
+        let path = args.value("history").unwrap_or(history::PATH);
+        written &= report_write(path, append_json_lines(Path::new(path), &sink.records));
+    }
+    ok && written
+}
+
+/// Makes `records` fail the run if the mode's `ok` does. A mode that
+/// recorded no cases gets a single one standing for the whole run, and one
+/// that failed without a failing record, such as on a write error outside
+/// any case, gets a failing one, so expectations can't turn it into a pass.
+fn settle(mode: &str, records: &mut Vec<Record>, ok: bool) {
+    let unrecorded = !ok && records.iter().all(|r| r.status != Status::Failed);
+    if records.is_empty() || unrecorded {
+        let mut record = Record::bare(mode.to_string());
+        record.mode = mode.to_string();
+        if !ok {
+            record.fail("failed, see the log");
+        }
+        record.status = if ok { Status::Passed } else { Status::Failed };
+        records.push(record);
+    }
+}
+
+fn report_write(path: &str, result: io::Result<()>) -> bool {
+    match result {
+        Ok(()) => true,
+        Err(err) => {
+            eprintln!("failed to write {path}: {err}");
+            false
+        }
+    }
+}
+
+fn append_json_lines(path: &Path, records: &[Record]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    for record in records {
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+    }
+    Ok(())
+}
+
+/// How a record shows up in JUnit: as a `failure` or `skipped` element with
+/// a message and details, or as a plain pass. Expected failures are
+/// skipped and unexpected passes fail, so CI goes red on exactly what the
+/// expectations don't account for.
+fn junit_result(record: &Record) -> Option<(&'static str, String, String)> {
+    match (record.status, &record.expected) {
+        (Status::Passed, None) => None,
+        (Status::Passed, Some(location)) => Some((
+            "failure",
+            format!("unexpected pass, expected to fail by {location}"),
+            String::new(),
+        )),
+        (Status::Failed, expected) => {
+            let reasons = record.reasons();
+            let first = reasons[0].lines().next().unwrap_or_default();
+            Some(match expected {
+                Some(location) => (
+                    "skipped",
+                    format!("expected failure ({location}): {first}"),
+                    reasons.join("\n"),
+                ),
+                None => ("failure", first.to_string(), reasons.join("\n")),
+            })
+        }
+        (Status::Skipped, _) => Some((
+            "skipped",
+            record.skipped.clone().unwrap_or_default(),
+            String::new(),
+        )),
+    }
+}
+
+/// One `testsuite` per adapter, one `testcase` per record, with the
+/// scenario parameters and instance settings as testcase properties.
+fn junit(records: &[Record]) -> String {
+    let mut suites: BTreeMap<String, Vec<&Record>> = BTreeMap::new();
+    for record in records {
+        let adapter = record
+            .adapter
+            .as_ref()
+            .map_or_else(|| "no adapter".to_string(), AdapterSummary::label);
+        suites.entry(adapter).or_default().push(record);
+    }
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>\n");
+    for (adapter, records) in suites {
+        let count = |element| {
+            records
+                .iter()
+                .filter(|r| junit_result(r).is_some_and(|(e, _, _)| e == element))
+                .count()
+        };
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.6}\">",
+            escape(&adapter),
+            records.len(),
+            count("failure"),
+            count("skipped"),
+            records.iter().map(|r| r.seconds()).sum::<f64>(),
+        );
+        let _ = writeln!(
+            xml,
+            "    <properties>\n      <property name=\"wgpu_revision\" value=\"{}\"/>\n    </properties>",
+            escape(WGPU_REVISION),
+        );
+        for record in records {
+            let _ = writeln!(
+                xml,
+                "    <testcase classname=\"{}\" name=\"{}\" time=\"{:.6}\">",
+                escape(&record.mode),
+                escape(&record.case),
+                record.seconds(),
+            );
+            if !record.parameters.is_empty() || !record.instance.is_empty() {
+                xml.push_str("      <properties>\n");
+                for (name, value) in record.parameters.iter().chain(&record.instance) {
+                    let _ = writeln!(
+                        xml,
+                        "        <property name=\"{}\" value=\"{}\"/>",
+                        escape(name),
+                        escape(value),
+                    );
+                }
+                xml.push_str("      </properties>\n");
+            }
+            if let Some((element, message, details)) = junit_result(record) {
+                let _ = writeln!(
+                    xml,
+                    "      <{element} message=\"{}\">{}</{element}>",
+                    escape(&message),
+                    escape(&details),
+                );
+            }
+            let logs: Vec<_> = record
+                .errors
+                .iter()
+                .flat_map(|err| {
+                    err.logs
+                        .iter()
+                        .map(move |line| format!("{}: {line}", err.step))
+                })
+                .collect();
+            if !logs.is_empty() {
+                let _ = writeln!(
+                    xml,
+                    "      <system-out>{}</system-out>",
+                    escape(&logs.join("\n"))
+                );
+            }
+            xml.push_str("    </testcase>\n");
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Not allowed in XML 1.0 at all, even as character references.
+            c if c < ' ' && !matches!(c, '\t' | '\n' | '\r') => escaped.push('\u{fffd}'),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(case: &str, status: Status) -> Record {
+        Record {
+            mode: "test".to_string(),
+            status,
+            ..Record::bare(case.to_string())
+        }
+    }
+
+    #[test]
+    fn escape_markup_and_control_characters() {
+        assert_eq!(
+            escape(r#"<a href="x">'&'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;&apos;&amp;&apos;&lt;/a&gt;"
+        );
+        assert_eq!(escape("tab\tnl\ncr\r"), "tab\tnl\ncr\r");
+        assert_eq!(escape("nul\0esc\u{1b}"), "nul\u{fffd}esc\u{fffd}");
+    }
+
+    #[test]
+    fn reasons_in_order() {
+        let mut record = record("case", Status::Failed);
+        record.errors.push(ErrorRecord {
+            step: "render".to_string(),
+            message: "Validation Error\n\nCaused by: ...".to_string(),
+            logs: Vec::new(),
+        });
+        record.comparison = Some(Comparison {
+            mismatched: 3,
+            max_diff: 40,
+        });
+        record.fail("no readback");
+        assert_eq!(
+            record.reasons(),
+            [
+                "error in render: Validation Error\n\nCaused by: ...",
+                "3 texels differ from the reference, by up to 40",
+                "no readback",
+            ]
+        );
+        record.comparison = Some(Comparison::default());
+        assert_eq!(record.reasons().len(), 2);
+    }
+
+    #[test]
+    fn junit_results() {
+        assert_eq!(junit_result(&record("pass", Status::Passed)), None);
+
+        let mut failed = record("fail", Status::Failed);
+        failed.fail("first line\nsecond line");
+        failed.fail("other");
+        assert_eq!(
+            junit_result(&failed),
+            Some((
+                "failure",
+                "first line".to_string(),
+                "first line\nsecond line\nother".to_string()
+            ))
+        );
+        failed.expected = Some("expectations.txt:3".to_string());
+        let (element, message, _) = junit_result(&failed).unwrap();
+        assert_eq!(element, "skipped");
+        assert_eq!(message, "expected failure (expectations.txt:3): first line");
+
+        let mut passed = record("pass", Status::Passed);
+        passed.expected = Some("expectations.txt:3".to_string());
+        let (element, message, _) = junit_result(&passed).unwrap();
+        assert_eq!(element, "failure");
+        assert_eq!(
+            message,
+            "unexpected pass, expected to fail by expectations.txt:3"
+        );
+
+        let mut skipped = record("skip", Status::Skipped);
+        skipped.skipped = Some("no adapter".to_string());
+        assert_eq!(
+            junit_result(&skipped),
+            Some(("skipped", "no adapter".to_string(), String::new()))
+        );
+    }
+
+    #[test]
+    fn junit_document() {
+        let mut failed = record("a <b>", Status::Failed);
+        failed.fail("x & y");
+        let mut skipped = record("c", Status::Skipped);
+        skipped.skipped = Some("why".to_string());
+        let xml = junit(&[record("p", Status::Passed), failed, skipped]);
+        assert!(xml.contains(r#"<testsuite name="no adapter" tests="3" failures="1" skipped="1""#));
+        assert!(xml.contains(r#"<testcase classname="test" name="a &lt;b&gt;""#));
+        assert!(xml.contains(r#"<failure message="x &amp; y">x &amp; y</failure>"#));
+        assert!(xml.contains(r#"<skipped message="why"></skipped>"#));
+        assert_eq!(xml.matches("<testcase ").count(), 3);
+        assert_eq!(xml.matches("</testcase>").count(), 3);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        for status in [Status::Passed, Status::Failed, Status::Skipped] {
+            let mut original = record("case", status);
+            original
+                .parameters
+                .insert("format".to_string(), "Rgba8Unorm".to_string());
+            original.fail("reason");
+            original.skipped = Some("why".to_string());
+            let json = serde_json::to_string(&original).unwrap();
+            let parsed: Record = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed.status, status);
+            assert_eq!(parsed.case, original.case);
+            assert_eq!(parsed.parameters, original.parameters);
+            assert_eq!(parsed.failures, original.failures);
+            assert_eq!(parsed.skipped, original.skipped);
+        }
+        let status: Status = serde_json::from_str(r#""skipped""#).unwrap();
+        assert_eq!(status, Status::Skipped);
+    }
+
+    #[test]
+    fn settle_records_a_failed_mode() {
+        let mut records = Vec::new();
+        settle("test", &mut records, true);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].status, Status::Passed);
+
+        // Failed outside its cases, e.g. writing an artifact.
+        let mut records = vec![record("p", Status::Passed), record("s", Status::Skipped)];
+        settle("test", &mut records, false);
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[2].status, Status::Failed);
+        assert!(!expectations::apply(&[], &mut records));
+
+        // Already carried by a failing case.
+        let mut records = vec![record("f", Status::Failed)];
+        settle("test", &mut records, false);
+        assert_eq!(records.len(), 1);
+    }
+}
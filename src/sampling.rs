@@ -0,0 +1,50 @@
+//! `sampling`: filtered reads of `tex11` through an sRGB view, at fractional
+//! offsets, against the CPU nearest/bilinear reference.
+//!
+//! The render target is viewed as its own format so only the source side is
+//! reinterpreted.
+
+use wgpu::FilterMode;
+
+use crate::harness::{self, Gpu};
+use crate::reference;
+use crate::results::{self, Record};
+use crate::scenario::{Read, Sampling, Scenario};
+
+/// Filtering only guarantees 8 bits of sub-texel weight precision, and some
+/// backends decode sRGB with a table, so linear results drift a few steps.
+pub const TOLERANCE: u8 = 3;
+
+/// Fragment-center offsets in texels. None land exactly on a texel edge,
+/// where nearest filtering may round either way.
+pub const OFFSETS: [[f32; 2]; 3] = [[0.0, 0.0], [0.25, 0.75], [0.6, 0.1]];
+
+/// Returns `false` if any case raised errors or disagreed with the reference.
+pub async fn run(gpu: &Gpu, scenario: &Scenario) -> bool {
+    let mut ok = true;
+    for filter in [FilterMode::Nearest, FilterMode::Linear] {
+        for explicit_level in [false, true] {
+            for offset in OFFSETS {
+                let sampling = Sampling {
+                    filter,
+                    explicit_level,
+                    offset,
+                };
+                let scenario = Scenario {
+                    view_format: scenario.format,
+                    source_view_format: Some(scenario.format.add_srgb_suffix()),
+                    read: Read::Sample(sampling),
+                    ..scenario.clone()
+                };
+                println!("== {sampling:?}");
+                let outcome = harness::run(gpu, &scenario).await;
+                outcome.print();
+                let mut record = Record::new(gpu, format!("{sampling:?}"), &scenario);
+                record.add_outcome(&outcome);
+                record.compare(&outcome, &reference::render(&scenario), TOLERANCE);
+                ok &= results::push(record);
+            }
+        }
+    }
+    ok
+}
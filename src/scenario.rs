@@ -0,0 +1,333 @@
+//! What a run of the repro is made of. `Scenario::default()` is the original
+//! CTS case: render `tex11` into an `Rgba8UnormSrgb` view of an `Rgba8Unorm`
+//! texture.
+//!
+//! `--scenario=PATH` hands every mode the default scenario with the
+//! settings of a scenario file applied, see [`parse`]:
+//!
+//! ```text
+//! # Backend validation on, and DXC for DX12.
+//! instance-flags = debug,validation
+//! dx12-compiler = dxc
+//! ```
+
+use std::fs;
+
+use wgpu::{
+    BlendState, Color, ColorWrites, FilterMode, LoadOp, StoreOp, TextureFormat, TextureSampleType,
+    TextureViewDimension,
+};
+
+use crate::cli::Args;
+use crate::instance;
+
+/// Width and height of `tex01` and `tex11`.
+pub const SIZE: u32 = 16;
+
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    /// Format of `tex01` and `tex11`.
+    pub format: TextureFormat,
+    /// Format `view01` reinterprets `tex01` as.
+    pub view_format: TextureFormat,
+    /// Format `view11` reinterprets `tex11` as; `None` reads it as `format`.
+    pub source_view_format: Option<TextureFormat>,
+    /// How the fragment shader reads `tex11`.
+    pub read: Read,
+    /// Where the render pipeline's bind group layout comes from.
+    pub layout: Layout,
+    /// Blending of the fragment output into `view01`; `None` replaces.
+    pub blend: Option<BlendState>,
+    /// How `view01` starts the pass. `LoadOp::Load` first uploads
+    /// `reference::destination()` into `tex01`.
+    pub load: LoadOp<Color>,
+    /// `StoreOp::Discard` leaves `tex01` zeroed for the readback.
+    pub store: StoreOp,
+    /// Whether the pass draws at all; without the draw only `load` and
+    /// `store` decide what `tex01` ends up holding.
+    pub draw: bool,
+    /// Channels of `view01` the draw writes; the rest keep their initial value.
+    pub write_mask: ColorWrites,
+    /// `set_viewport` region; `None` keeps the full-target default.
+    pub viewport: Option<Rect>,
+    /// `set_scissor_rect` region; `None` keeps the full-target default.
+    pub scissor: Option<Rect>,
+    /// `PipelineCompilationOptions` of the render pipeline.
+    pub compilation: Compilation,
+    /// Where the run calls `Device::destroy`, if anywhere.
+    pub destroy: Option<Destroy>,
+    /// What `harness::init` creates the `Instance` with; the command line's
+    /// settings unless a scenario file sets them.
+    pub instance: instance::Settings,
+}
+
+impl Scenario {
+    /// Whether `blend` reads the fragment shader's second output, which
+    /// selects `SHADER_DUAL_SOURCE` and needs `Features::DUAL_SOURCE_BLENDING`.
+    pub fn dual_source(&self) -> bool {
+        self.blend.is_some_and(|blend| {
+            [blend.color, blend.alpha].iter().any(|c| {
+                c.src_factor.ref_second_blend_source() || c.dst_factor.ref_second_blend_source()
+            })
+        })
+    }
+
+    /// Whether `compilation.constants` selects `SHADER_OVERRIDE`, which only
+    /// replaces the plain `textureLoad` shader.
+    pub fn overridden(&self) -> bool {
+        self.read == Read::Load && !self.dual_source() && !self.compilation.constants.is_empty()
+    }
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Scenario {
+            format: TextureFormat::Rgba8Unorm,
+            view_format: TextureFormat::Rgba8UnormSrgb,
+            source_view_format: None,
+            read: Read::Load,
+            layout: Layout::Auto,
+            blend: None,
+            load: LoadOp::Clear(Color::TRANSPARENT),
+            store: StoreOp::Store,
+            draw: true,
+            write_mask: ColorWrites::ALL,
+            viewport: None,
+            scissor: None,
+            compilation: Compilation::default(),
+            destroy: None,
+            instance: instance::configured(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Destroy {
+    /// Between the upload's submit and creating the views.
+    AfterUpload,
+    /// With the render commands encoded but not yet submitted.
+    BeforeSubmit,
+    /// With the readback's `map_async` pending.
+    DuringMap,
+}
+
+/// Both stages share the booleans; only the fragment stage gets `constants`,
+/// since `SHADER01` declares no overrides.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Compilation {
+    /// `override` values for `SHADER_OVERRIDE`, by name or numeric id.
+    pub constants: Vec<(String, f64)>,
+    pub zero_initialize_workgroup_memory: bool,
+    pub vertex_pulling_transform: bool,
+}
+
+impl Default for Compilation {
+    /// What wgpu's `PipelineCompilationOptions::default()` uses.
+    fn default() -> Self {
+        Compilation {
+            constants: Vec::new(),
+            zero_initialize_workgroup_memory: true,
+            vertex_pulling_transform: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Read {
+    /// `textureLoad` at the fragment's texel, as in `SHADER11`.
+    Load,
+    /// `textureSample`/`textureSampleLevel` through a `Sampler`, as in `SHADER_SAMPLE`.
+    Sample(Sampling),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sampling {
+    /// Used for both `mag_filter` and `min_filter`.
+    pub filter: FilterMode,
+    /// `textureSampleLevel(.., 0.0)` instead of `textureSample`.
+    pub explicit_level: bool,
+    /// Added to each fragment's center, in texels.
+    pub offset: [f32; 2],
+}
+
+/// A texel-aligned region of `tex01`. Viewports use it too, so the
+/// full-screen quad covers whole texels and coverage stays predictable.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Layout {
+    /// `layout: None`, bind group built from `get_bind_group_layout(0)`.
+    Auto,
+    /// A `BindGroupLayout`/`PipelineLayout` pair created up front.
+    Explicit(TextureBinding),
+}
+
+/// The `BindingType::Texture` used for `src` in an explicit layout.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureBinding {
+    pub sample_type: TextureSampleType,
+    pub view_dimension: TextureViewDimension,
+    pub multisampled: bool,
+}
+
+impl Default for TextureBinding {
+    /// What `texture_2d<f32>` should derive to.
+    fn default() -> Self {
+        TextureBinding {
+            sample_type: TextureSampleType::Float { filterable: true },
+            view_dimension: TextureViewDimension::D2,
+            multisampled: false,
+        }
+    }
+}
+
+pub fn parse_sample_type(s: &str) -> Option<TextureSampleType> {
+    Some(match s {
+        "float" => TextureSampleType::Float { filterable: true },
+        "unfilterable-float" => TextureSampleType::Float { filterable: false },
+        "depth" => TextureSampleType::Depth,
+        "sint" => TextureSampleType::Sint,
+        "uint" => TextureSampleType::Uint,
+        _ => return None,
+    })
+}
+
+pub fn parse_view_dimension(s: &str) -> Option<TextureViewDimension> {
+    Some(match s {
+        "1d" => TextureViewDimension::D1,
+        "2d" => TextureViewDimension::D2,
+        "2d-array" => TextureViewDimension::D2Array,
+        "cube" => TextureViewDimension::Cube,
+        "cube-array" => TextureViewDimension::CubeArray,
+        "3d" => TextureViewDimension::D3,
+        _ => return None,
+    })
+}
+
+/// The scenario `--scenario=PATH` describes, or the default one without
+/// it. Exits with a usage error on a file it can't read or parse.
+pub fn load(args: &Args) -> Scenario {
+    let Some(path) = args.value("scenario") else {
+        return Scenario::default();
+    };
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) => {
+            eprintln!("failed to read {path}: {err}");
+            std::process::exit(2);
+        }
+    };
+    match parse(&text, Scenario::default()) {
+        Ok(scenario) => scenario,
+        Err((line, message)) => {
+            eprintln!("{path}:{line}: {message}");
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Applies the `key = value` lines of a scenario file to `scenario`,
+/// skipping blank lines and `#` comments. The keys are the instance
+/// options, `instance-flags`, `dx12-compiler` and `gles-minor-version`,
+/// taking the same values.
+///
+/// On error, returns the 1-based line number and what is wrong with it.
+pub fn parse(text: &str, mut scenario: Scenario) -> Result<Scenario, (usize, String)> {
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err((index + 1, "expected `key = value`".to_string()));
+        };
+        let (key, value) = (key.trim(), value.trim());
+        let settings = &mut scenario.instance;
+        let parsed = match key {
+            "instance-flags" => instance::parse_flags(value).map(|flags| settings.flags = flags),
+            "dx12-compiler" => instance::parse_dx12_compiler(value)
+                .map(|compiler| settings.dx12_shader_compiler = compiler),
+            "gles-minor-version" => instance::parse_gles_minor_version(value)
+                .map(|version| settings.gles_minor_version = version),
+            _ => {
+                return Err((
+                    index + 1,
+                    format!(
+                        "unknown key `{key}`, expected instance-flags, dx12-compiler or \
+                         gles-minor-version"
+                    ),
+                ))
+            }
+        };
+        if parsed.is_none() {
+            return Err((index + 1, format!("unrecognized value for {key}: {value}")));
+        }
+    }
+    Ok(scenario)
+}
+
+#[cfg(test)]
+mod tests {
+    use wgpu::{Dx12Compiler, Gles3MinorVersion, InstanceFlags};
+
+    use super::*;
+
+    #[test]
+    fn parse_instance_settings() {
+        let text = "
+            # Backend validation on.
+            instance-flags = debug,validation
+
+            dx12-compiler=dxc
+            gles-minor-version = 1
+        ";
+        let scenario = parse(text, Scenario::default()).unwrap();
+        let settings = &scenario.instance;
+        assert_eq!(
+            settings.flags,
+            InstanceFlags::DEBUG | InstanceFlags::VALIDATION
+        );
+        assert!(matches!(
+            settings.dx12_shader_compiler,
+            Dx12Compiler::Dxc { .. }
+        ));
+        assert_eq!(settings.gles_minor_version, Gles3MinorVersion::Version1);
+        // The rest of the scenario is the base it was applied to.
+        assert_eq!(scenario.format, Scenario::default().format);
+    }
+
+    #[test]
+    fn parse_keeps_what_the_file_leaves_out() {
+        let mut base = Scenario::default();
+        base.instance.flags = InstanceFlags::VALIDATION;
+        let scenario = parse("instance-flags = none\n", base.clone()).unwrap();
+        assert_eq!(scenario.instance.flags, InstanceFlags::empty());
+        let scenario = parse("gles-minor-version = 2\n", base).unwrap();
+        assert_eq!(scenario.instance.flags, InstanceFlags::VALIDATION);
+    }
+
+    #[test]
+    fn parse_rejects_malformed_lines() {
+        let error = |text| parse(text, Scenario::default()).unwrap_err();
+        assert_eq!(error("instance-flags").0, 1);
+        assert_eq!(
+            error("# comment\ninstance-flags = loud"),
+            (2, "unrecognized value for instance-flags: loud".to_string())
+        );
+        let (line, message) = error("\nformat = rgba8unorm");
+        assert_eq!(line, 2);
+        assert!(message.starts_with("unknown key `format`"));
+    }
+}
@@ -0,0 +1,94 @@
+//! WGSL sources handed to `create_shader_module` by the scenarios.
+
+/// Full-screen triangle pair, no vertex buffers.
+pub const SHADER01: &str = "
+@vertex fn main(@builtin(vertex_index) VertexIndex : u32) -> @builtin(position) vec4<f32> {
+    var pos = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(-1.0,  1.0),
+        vec2<f32>( 1.0, -1.0),
+        vec2<f32>(-1.0,  1.0),
+        vec2<f32>( 1.0, -1.0),
+        vec2<f32>( 1.0,  1.0)
+    );
+    return vec4<f32>(pos[VertexIndex], 0.0, 1.0);
+}";
+
+/// Copies `src` texel-for-texel into the color attachment.
+pub const SHADER11: &str = "
+@group(0) @binding(0) var src: texture_2d<f32>;
+@fragment fn main(@builtin(position) coord: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureLoad(src, vec2<i32>(coord.xy), 0);
+}";
+
+/// `SHADER11` with filtering: samples `src` at each fragment's center moved
+/// by `offset` texels, through either entry point.
+pub const SHADER_SAMPLE: &str = "
+@group(0) @binding(0) var src: texture_2d<f32>;
+@group(0) @binding(1) var smp: sampler;
+@group(0) @binding(2) var<uniform> offset: vec2<f32>;
+
+fn uv(coord: vec4<f32>) -> vec2<f32> {
+    return (coord.xy + offset) / vec2<f32>(textureDimensions(src));
+}
+
+@fragment fn fs_sample(@builtin(position) coord: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureSample(src, smp, uv(coord));
+}
+
+@fragment fn fs_sample_level(@builtin(position) coord: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureSampleLevel(src, smp, uv(coord), 0.0);
+}";
+
+/// `SHADER11` with a second blend source for dual-source blending, derived
+/// from the loaded texel as `vec4(1.0 - rgb, a * 0.5)`.
+pub const SHADER_DUAL_SOURCE: &str = "
+@group(0) @binding(0) var src: texture_2d<f32>;
+
+struct Output {
+    @location(0) color: vec4<f32>,
+    @location(0) @second_blend_source blend: vec4<f32>,
+}
+
+@fragment fn main(@builtin(position) coord: vec4<f32>) -> Output {
+    let texel = textureLoad(src, vec2<i32>(coord.xy), 0);
+    return Output(texel, vec4<f32>(1.0 - texel.rgb, texel.a * 0.5));
+}";
+
+/// `SHADER11` adjusted by pipeline-overridable constants, selected when a
+/// scenario sets any. `alpha` is keyed by its numeric id.
+pub const SHADER_OVERRIDE: &str = "
+override gain: f32 = 1.0;
+override bias: f32 = 0.0;
+@id(0) override alpha: f32 = 1.0;
+@group(0) @binding(0) var src: texture_2d<f32>;
+@fragment fn main(@builtin(position) coord: vec4<f32>) -> @location(0) vec4<f32> {
+    let texel = textureLoad(src, vec2<i32>(coord.xy), 0);
+    return vec4<f32>(texel.rgb * gain + bias, texel.a * alpha);
+}";
+
+/// Compute counterpart of `SHADER11`: the same `textureLoad`, written out
+/// through a storage texture and a storage buffer instead of an attachment.
+pub const SHADER_COMPUTE: &str = "
+@group(0) @binding(0) var src: texture_2d<f32>;
+@group(0) @binding(1) var dst: texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(2) var<storage, read_write> out: array<vec4<f32>>;
+@compute @workgroup_size(8, 8) fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(src);
+    if any(id.xy >= size) {
+        return;
+    }
+    let texel = textureLoad(src, vec2<i32>(id.xy), 0);
+    textureStore(dst, vec2<i32>(id.xy), texel);
+    out[id.y * size.x + id.x] = texel;
+}";
+
+/// Every shader used by any scenario, by name.
+pub const ALL: &[(&str, &str)] = &[
+    ("shader01", SHADER01),
+    ("shader11", SHADER11),
+    ("shader_sample", SHADER_SAMPLE),
+    ("shader_dual_source", SHADER_DUAL_SOURCE),
+    ("shader_override", SHADER_OVERRIDE),
+    ("shader_compute", SHADER_COMPUTE),
+];
@@ -0,0 +1,116 @@
+//! `stress [--threads=N] [--iterations=N] [--timeout=SECS]`: run the
+//! scenario from many threads at once on one `Device`/`Queue`, each run
+//! creating its own resources and pipelines, and verify every readback.
+//!
+//! Error scopes belong to the device rather than to a thread, so a
+//! validation error may be attributed to another thread's run. Each thread
+//! is judged on its pixels only, and the errors of all of them on one
+//! `errors` record once every thread has finished.
+
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::cli::Args;
+use crate::harness;
+use crate::reference;
+use crate::results::{self, Record};
+use crate::scenario::Scenario;
+
+const THREADS: usize = 8;
+const ITERATIONS: usize = 16;
+const TIMEOUT: u64 = 60;
+
+/// What one thread saw over its iterations.
+#[derive(Debug, Default)]
+struct Tally {
+    runs: usize,
+    /// Runs whose pixels disagreed with the reference.
+    mismatched: usize,
+    /// Runs without a readback.
+    missing: usize,
+    /// Validation errors popped by this thread's error scopes, which may
+    /// have been raised by another thread's runs.
+    errors: usize,
+}
+
+/// Returns `false` if any run failed, or if the threads didn't all finish
+/// within the timeout, which likely means a deadlock.
+pub fn run(args: &Args, scenario: &Scenario) -> bool {
+    let count = |s: &str| s.parse().ok().filter(|&n| n > 0);
+    let threads = args.parse("threads", count).unwrap_or(THREADS);
+    let iterations = args.parse("iterations", count).unwrap_or(ITERATIONS);
+    let timeout = Duration::from_secs(args.parse("timeout", |s| s.parse().ok()).unwrap_or(TIMEOUT));
+
+    let gpu = Arc::new(pollster::block_on(harness::init(scenario)));
+    println!("{:?}", gpu.adapter.get_info());
+    println!("{threads} threads x {iterations} iterations");
+    let expected = Arc::new(reference::render(scenario));
+    let (sender, receiver) = mpsc::channel();
+    for index in 0..threads {
+        let (gpu, scenario, expected) = (gpu.clone(), scenario.clone(), expected.clone());
+        let sender = sender.clone();
+        // Never joined: a deadlocked thread must not hang the report.
+        thread::spawn(move || {
+            let mut tally = Tally::default();
+            for _ in 0..iterations {
+                let outcome = pollster::block_on(harness::run(&gpu, &scenario));
+                tally.runs += 1;
+                tally.errors += outcome.errors.len();
+                match &outcome.pixels {
+                    Some(pixels) if reference::compare(&expected, pixels).matches() => {}
+                    Some(_) => tally.mismatched += 1,
+                    None => tally.missing += 1,
+                }
+            }
+            let _ = sender.send((index, tally));
+        });
+    }
+    drop(sender);
+
+    let deadline = Instant::now() + timeout;
+    let mut ok = true;
+    let mut finished = 0;
+    let mut errors = 0;
+    while finished < threads {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match receiver.recv_timeout(remaining) {
+            Ok((index, tally)) => {
+                println!("thread {index}: {tally:?}");
+                let mut record = Record::new(&gpu, format!("thread {index}"), scenario);
+                if tally.mismatched != 0 || tally.missing != 0 {
+                    record.fail(format!("{tally:?}"));
+                }
+                errors += tally.errors;
+                ok &= results::push(record);
+                finished += 1;
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                let reason = format!(
+                    "{} of {threads} threads still running after {timeout:?}, likely deadlocked",
+                    threads - finished
+                );
+                println!("{reason}");
+                let mut record = Record::new(&gpu, "deadlock", scenario);
+                record.fail(reason);
+                results::push(record);
+                return false;
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                println!("{} threads panicked", threads - finished);
+                let mut record = Record::new(&gpu, "panics", scenario);
+                record.fail(format!("{} threads panicked", threads - finished));
+                results::push(record);
+                return false;
+            }
+        }
+    }
+
+    println!("{errors} validation errors across all threads");
+    let mut record = Record::new(&gpu, "errors", scenario);
+    if errors != 0 {
+        record.fail(format!("{errors} validation errors across all threads"));
+    }
+    results::push(record) && ok
+}
@@ -0,0 +1,194 @@
+//! Offline shader validation and cross-compilation with naga, no GPU needed.
+
+use std::fs;
+use std::path::Path;
+
+use naga::{Binding, Module, ShaderStage, TypeInner};
+use similar::TextDiff;
+
+use crate::shaders;
+
+/// Translation with the naga that the pinned wgpu uses.
+pub mod current {
+    include!("translate/backends.rs");
+}
+
+/// The same translation with naga from the last good wgpu revision.
+pub mod good {
+    use naga_good as naga;
+    include!("translate/backends.rs");
+}
+
+/// `shaders [OUT_DIR]`: validate every scenario shader, print what naga
+/// sees and write the per-backend translations to `out_dir`.
+///
+/// Returns `false` if any shader failed to parse, validate or translate.
+pub fn run(out_dir: &Path) -> bool {
+    if let Err(err) = fs::create_dir_all(out_dir) {
+        println!("ERROR: {}: {err}", out_dir.display());
+        return false;
+    }
+    let mut ok = true;
+    for &(name, source) in shaders::ALL {
+        println!("== {name}");
+        let (module, info) = match current::parse(source) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                println!("ERROR: {err}");
+                ok = false;
+                continue;
+            }
+        };
+        print_module(&module);
+        for (file, output) in current::translate(&module, &info) {
+            match output {
+                Ok(contents) => {
+                    let path = out_dir.join(format!("{name}.{file}"));
+                    match fs::write(&path, contents) {
+                        Ok(()) => println!("wrote {}", path.display()),
+                        Err(err) => {
+                            println!("ERROR: {}: {err}", path.display());
+                            ok = false;
+                        }
+                    }
+                }
+                Err(err) => {
+                    println!("ERROR: {name}.{file}: {err}");
+                    ok = false;
+                }
+            }
+        }
+    }
+    ok
+}
+
+/// `shaders-diff [OUT_DIR]`: translate every scenario shader with both the
+/// good and the bad revision's naga and write a unified diff per output.
+///
+/// Returns `false` if either naga failed on a shader.
+pub fn diff(out_dir: &Path) -> bool {
+    if let Err(err) = fs::create_dir_all(out_dir) {
+        println!("ERROR: {}: {err}", out_dir.display());
+        return false;
+    }
+    let mut ok = true;
+    for &(name, source) in shaders::ALL {
+        let good = good::parse(source).map(|(module, info)| good::translate(&module, &info));
+        let bad = current::parse(source).map(|(module, info)| current::translate(&module, &info));
+        let (good, bad) = match (good, bad) {
+            (Ok(good), Ok(bad)) => (good, bad),
+            (good, bad) => {
+                if let Err(err) = good {
+                    println!("ERROR: {name} (good): {err}");
+                }
+                if let Err(err) = bad {
+                    println!("ERROR: {name} (bad): {err}");
+                }
+                ok = false;
+                continue;
+            }
+        };
+        for ((file, good), (_, bad)) in good.into_iter().zip(bad) {
+            let (good, bad) = match (good, bad) {
+                (Ok(good), Ok(bad)) => (as_text(&file, good), as_text(&file, bad)),
+                (good, bad) => {
+                    println!(
+                        "{name}.{file}: good {}, bad {}",
+                        good.err().as_deref().unwrap_or("ok"),
+                        bad.err().as_deref().unwrap_or("ok"),
+                    );
+                    ok = false;
+                    continue;
+                }
+            };
+            if good == bad {
+                println!("{name}.{file}: identical");
+                continue;
+            }
+            let path = out_dir.join(format!("{name}.{file}.diff"));
+            let diff = TextDiff::from_lines(&good, &bad)
+                .unified_diff()
+                .header(
+                    &format!("good/{name}.{file}"),
+                    &format!("bad/{name}.{file}"),
+                )
+                .to_string();
+            match fs::write(&path, diff) {
+                Ok(()) => println!("{name}.{file}: differs, wrote {}", path.display()),
+                Err(err) => {
+                    println!("ERROR: {}: {err}", path.display());
+                    ok = false;
+                }
+            }
+        }
+    }
+    ok
+}
+
+/// SPIR-V is diffed one hex word per line; everything else is already text.
+fn as_text(file: &str, contents: Vec<u8>) -> String {
+    if file == "spv" {
+        contents
+            .chunks(4)
+            .map(|w| format!("{:08x}\n", u32::from_le_bytes(w.try_into().unwrap())))
+            .collect()
+    } else {
+        String::from_utf8(contents).unwrap()
+    }
+}
+
+fn print_module(module: &Module) {
+    for ep in &module.entry_points {
+        println!("entry point {:?} `{}`", ep.stage, ep.name);
+        if ep.stage == ShaderStage::Compute {
+            println!("  workgroup_size {:?}", ep.workgroup_size);
+        }
+        for arg in &ep.function.arguments {
+            let name = arg.name.as_deref().unwrap_or("_");
+            for binding in bindings(module, arg.ty, arg.binding.as_ref()) {
+                println!("  in  {binding} {name}");
+            }
+        }
+        if let Some(result) = &ep.function.result {
+            for binding in bindings(module, result.ty, result.binding.as_ref()) {
+                println!("  out {binding}");
+            }
+        }
+    }
+    for (_, var) in module.global_variables.iter() {
+        if let Some(rb) = &var.binding {
+            println!(
+                "@group({}) @binding({}) {}: {:?}",
+                rb.group,
+                rb.binding,
+                var.name.as_deref().unwrap_or("_"),
+                module.types[var.ty].inner,
+            );
+        }
+    }
+}
+
+/// Flattens an entry point argument or result into its IO bindings,
+/// descending into struct members.
+fn bindings(
+    module: &Module,
+    ty: naga::Handle<naga::Type>,
+    binding: Option<&Binding>,
+) -> Vec<String> {
+    match (binding, &module.types[ty].inner) {
+        (Some(binding), _) => vec![fmt_binding(binding)],
+        (None, TypeInner::Struct { members, .. }) => members
+            .iter()
+            .filter_map(|m| m.binding.as_ref())
+            .map(fmt_binding)
+            .collect(),
+        (None, _) => Vec::new(),
+    }
+}
+
+fn fmt_binding(binding: &Binding) -> String {
+    match binding {
+        Binding::BuiltIn(b) => format!("@builtin({b:?})"),
+        Binding::Location { location, .. } => format!("@location({location})"),
+    }
+}
@@ -0,0 +1,108 @@
+// Shared by `translate::current` and `translate::good`, each of which
+// brings its own `naga` into scope before including this file.
+
+use naga::back::{glsl, hlsl, msl, pipeline_constants, spv};
+use naga::valid::{Capabilities, ModuleInfo, ValidationFlags, Validator};
+use naga::{Module, ShaderStage};
+
+/// Parses and validates WGSL, rendering any diagnostic against `source`.
+pub fn parse(source: &str) -> Result<(Module, ModuleInfo), String> {
+    let module = naga::front::wgsl::parse_str(source).map_err(|e| e.emit_to_string(source))?;
+    let info = Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .map_err(|e| e.emit_to_string(source))?;
+    Ok((module, info))
+}
+
+/// Translates a validated module to every backend language wgpu uses.
+///
+/// Yields `(file suffix, contents)`; GLSL gets one file per entry point
+/// since it can only hold a single stage. Overrides keep their defaults,
+/// as the backends only accept modules with them resolved.
+pub fn translate(module: &Module, info: &ModuleInfo) -> Vec<(String, Result<Vec<u8>, String>)> {
+    let (module, info) =
+        match pipeline_constants::process_overrides(module, info, &Default::default()) {
+            Ok((module, info)) => (module, info),
+            Err(e) => return vec![("overrides".to_string(), Err(e.to_string()))],
+        };
+    let (module, info) = (module.as_ref(), info.as_ref());
+    let mut out = Vec::new();
+    out.push(("spv".to_string(), write_spv(module, info)));
+    for ep in &module.entry_points {
+        let ext = match ep.stage {
+            ShaderStage::Vertex => "vert",
+            ShaderStage::Fragment => "frag",
+            ShaderStage::Compute => "comp",
+        };
+        out.push((
+            format!("{}.{ext}", ep.name),
+            write_glsl(module, info, ep.stage, &ep.name).map(String::into_bytes),
+        ));
+    }
+    out.push((
+        "hlsl".to_string(),
+        write_hlsl(module, info).map(String::into_bytes),
+    ));
+    out.push((
+        "metal".to_string(),
+        write_msl(module, info).map(String::into_bytes),
+    ));
+    out
+}
+
+fn write_spv(module: &Module, info: &ModuleInfo) -> Result<Vec<u8>, String> {
+    let words =
+        spv::write_vec(module, info, &spv::Options::default(), None).map_err(|e| e.to_string())?;
+    Ok(words.iter().flat_map(|w| w.to_le_bytes()).collect())
+}
+
+fn write_glsl(
+    module: &Module,
+    info: &ModuleInfo,
+    shader_stage: ShaderStage,
+    entry_point: &str,
+) -> Result<String, String> {
+    let mut out = String::new();
+    let options = glsl::Options::default();
+    let pipeline_options = glsl::PipelineOptions {
+        shader_stage,
+        entry_point: entry_point.to_string(),
+        multiview: None,
+    };
+    glsl::Writer::new(
+        &mut out,
+        module,
+        info,
+        &options,
+        &pipeline_options,
+        naga::proc::BoundsCheckPolicies::default(),
+    )
+    .and_then(|mut w| w.write())
+    .map_err(|e| e.to_string())?;
+    Ok(out)
+}
+
+fn write_hlsl(module: &Module, info: &ModuleInfo) -> Result<String, String> {
+    let mut out = String::new();
+    let options = hlsl::Options::default();
+    hlsl::Writer::new(&mut out, &options)
+        .write(module, info, None)
+        .map_err(|e| e.to_string())?;
+    Ok(out)
+}
+
+fn write_msl(module: &Module, info: &ModuleInfo) -> Result<String, String> {
+    let options = msl::Options {
+        // Oldest version wgpu's Metal backend targets; 1.0 lacks dual-source blending.
+        lang_version: (1, 2),
+        ..Default::default()
+    };
+    msl::write_string(
+        module,
+        info,
+        &options,
+        &msl::PipelineOptions::default(),
+    )
+    .map(|(out, _)| out)
+    .map_err(|e| e.to_string())
+}